@@ -14,9 +14,11 @@ use self::{
 use crate::{
     defs::{Bitboard, Color, Colors, NrOf, Piece, Square, PIECE_VALUES},
     eval::psqt::{FLIP, PSQTS_EG, PSQTS_MG},
+    moves::defs::Move,
     utils::bit_ops::find_ones_u8,
 };
 
+#[derive(Clone)]
 pub struct Board {
     pub piece_bbs: [[Bitboard; NrOf::PIECE_TYPES]; Colors::BOTH],
     pub color_bbs: [Bitboard; Colors::BOTH],
@@ -72,6 +74,28 @@ impl Board {
         self.piece_bbs[color][Pieces::KING].trailing_zeros() as Square
     }
 
+    // whether the given side has any piece other than pawns and the king
+    pub fn has_non_pawn_material(&self, color: Color) -> bool {
+        let pieces = self.piece_bbs[color];
+        pieces[Pieces::QUEEN]
+            | pieces[Pieces::ROOK]
+            | pieces[Pieces::BISHOP]
+            | pieces[Pieces::KNIGHT]
+            > 0
+    }
+
+    // the move that led to the current position
+    pub fn last_move(&self) -> Option<Move> {
+        self.history.last().map(|s| s.next_move)
+    }
+
+    // whether the last move on the board was a null move
+    pub fn last_move_null(&self) -> bool {
+        self.history
+            .last()
+            .is_some_and(|s| s.next_move == Move::default())
+    }
+
     pub fn set_ep_square(&mut self, sq: Square) {
         self.state.ep_square = Some(sq);
         self.state.zobrist_hash ^= self.zobrist.en_passant_hash(sq);
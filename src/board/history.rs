@@ -4,6 +4,7 @@ use crate::defs::MAX_MOVE_COUNT;
 
 use super::state::State;
 
+#[derive(Clone)]
 pub struct History {
     list: [State; MAX_MOVE_COUNT],
     current: usize,
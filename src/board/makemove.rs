@@ -91,6 +91,21 @@ impl Board {
         !is_check
     }
 
+    // A null move passes the turn to the opponent without moving any piece. It's never legal in a
+    // game but it's used in the search to check how good a position is for the side to move.
+    pub fn make_null_move(&mut self) {
+        self.state.next_move = Move::default();
+        self.history.push(self.state);
+
+        self.clear_ep_square();
+        self.state.halfmove_count += 1;
+
+        self.state.active_color ^= 1;
+        self.state.zobrist_hash ^= self.zobrist.color_hash();
+
+        debug_assert_eq!(self.zobrist_from_scratch(), self.state.zobrist_hash);
+    }
+
     fn castle_rook(&mut self, king: Square) {
         let (rook_from, rook_to) = match king {
             Squares::C1 => (Squares::A1, Squares::D1),
@@ -192,6 +207,11 @@ impl Board {
         debug_assert_eq!(self.zobrist_from_scratch(), self.state.zobrist_hash);
     }
 
+    // no piece was moved, so restoring the previous state is enough
+    pub fn unmake_null_move(&mut self) {
+        self.state = self.history.pop();
+    }
+
     fn uncastle_rook(&mut self, king: Square) {
         let (rook_from, rook_to) = match king {
             Squares::C1 => (Squares::A1, Squares::D1),
@@ -205,3 +225,34 @@ impl Board {
         put_piece(self, Pieces::ROOK, color, rook_from);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::Board, moves::MoveGenerator};
+
+    #[test]
+    fn null_move() {
+        let mut mg = MoveGenerator::default();
+        mg.init();
+        let mut b = Board::new();
+        b.read_fen("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPP2PPP/RNBQKBNR b KQkq e3 0 3")
+            .unwrap();
+        let before = b.state;
+
+        b.make_null_move();
+        assert_eq!(b.state.active_color, before.active_color ^ 1);
+        assert_eq!(b.state.ep_square, None);
+        assert_eq!(b.state.zobrist_hash, b.zobrist_from_scratch());
+        assert!(b.last_move_null());
+
+        // moves can be played and unmade after a null move
+        let moves = mg.get_all_legal_moves(&b, false);
+        assert!(b.make_move(moves[0].m, &mg));
+        b.unmake();
+
+        b.unmake_null_move();
+        assert_eq!(b.state.zobrist_hash, before.zobrist_hash);
+        assert_eq!(b.state.ep_square, before.ep_square);
+        assert!(!b.last_move_null());
+    }
+}
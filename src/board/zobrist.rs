@@ -9,6 +9,7 @@ const SEED: [u8; 32] = [213; 32];
 // - one number to indicate the side to move is black
 // - four numbers for castling rights
 // - eight numbers for the file of a valid en passant square
+#[derive(Clone)]
 pub struct Zobrist {
     pieces: [[[u64; NrOf::SQUARES]; NrOf::PIECE_TYPES]; Colors::BOTH],
     color: u64,
@@ -54,10 +54,13 @@ impl Engine {
 mod tests {
     use std::sync::Arc;
 
+    use crossbeam_channel::Receiver;
+
     use crate::{
         defs::{Info, START_FEN},
-        engine::Engine,
-        search::defs::{SearchControl, SearchResult, SearchTime},
+        engine::{options::EngineOption, Engine},
+        search::defs::{GameTime, SearchControl, SearchLimits, SearchParams, SearchResult},
+        uci::defs::UciData,
     };
 
     #[test]
@@ -73,7 +76,90 @@ mod tests {
             do_test(fen, &mut engine);
         }
     }
+    #[test]
+    fn mate_search() {
+        let fen = "r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1";
+        let mut engine = Engine::new();
+        let rx = start_search_thread(fen, &mut engine);
+        engine.search.send(SearchControl::Start(SearchParams {
+            limits: SearchLimits {
+                mate: Some(2),
+                ..Default::default()
+            },
+            ..Default::default()
+        }));
+        let res = rx.recv().unwrap();
+        engine.quit();
+        match res {
+            Info::Search(SearchResult::BestMove(m, _)) => assert_eq!(m.to_string(), "d5f6"),
+            _ => panic!(),
+        };
+    }
+    #[test]
+    fn deterministic_search() {
+        let fen = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+        let mut engine = Engine::new();
+        let rx = start_search_thread(fen, &mut engine);
+        // the clock is measured in nodes, so the result doesn't depend on the timing
+        engine.options.lock().unwrap().nodes_time = 50;
+        let mut results = Vec::new();
+        for _ in 0..2 {
+            engine.uci_command(UciData::NewGame);
+            engine.search.send(SearchControl::Start(SearchParams {
+                limits: SearchLimits {
+                    clock: Some(GameTime {
+                        wtime: 20000,
+                        btime: 20000,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }));
+            match rx.recv().unwrap() {
+                Info::Search(SearchResult::BestMove(m, ponder)) => results.push((m, ponder)),
+                _ => panic!(),
+            }
+        }
+        engine.quit();
+        assert_eq!(results[0], results[1]);
+    }
+    #[test]
+    fn option_bounds() {
+        let mut engine = Engine::new();
+        let opts = [
+            EngineOption::RfpMargin(-50),
+            EngineOption::Threads(0),
+            EngineOption::SkillLevel(99),
+            EngineOption::Contempt(i16::MIN),
+        ];
+        for opt in opts {
+            engine.uci_command(UciData::Option(opt));
+        }
+        let options = engine.options.lock().unwrap();
+        assert_eq!(options.rfp_margin, 0);
+        assert_eq!(options.threads, 1);
+        assert_eq!(options.skill_level, 20);
+        assert_eq!(options.contempt, -100);
+    }
     fn do_test(fen: &str, engine: &mut Engine) {
+        let rx = start_search_thread(fen, engine);
+        engine.search.send(SearchControl::Start(SearchParams {
+            limits: SearchLimits {
+                depth: Some(5),
+                ..Default::default()
+            },
+            ponder: false,
+            search_moves: Vec::new(),
+        }));
+        let res = rx.recv().unwrap();
+        engine.quit();
+        match res {
+            Info::Search(SearchResult::BestMove(_, _)) => (),
+            _ => panic!(),
+        };
+    }
+    fn start_search_thread(fen: &str, engine: &mut Engine) -> Receiver<Info> {
         let (tx, rx) = crossbeam_channel::unbounded();
         engine.search.init(
             tx,
@@ -83,14 +169,6 @@ mod tests {
             Arc::clone(&engine.options),
         );
         engine.board.lock().unwrap().read_fen(fen).unwrap();
-        engine
-            .search
-            .send(SearchControl::Start(SearchTime::Depth(5)));
-        let res = rx.recv().unwrap();
-        engine.quit();
-        match res {
-            Info::Search(SearchResult::BestMove(_)) => (),
-            _ => panic!(),
-        };
+        rx
     }
 }
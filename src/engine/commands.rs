@@ -18,9 +18,25 @@ impl Engine {
                 Uci::output("uciok");
             }
 
-            UciData::NewGame => (), // TODO: clear the tt
+            UciData::NewGame => {
+                // nothing from the previous game is kept, so that searches are reproducible
+                self.tt.lock().expect(ErrFatal::LOCK).clear();
+                self.search.send(SearchControl::NewGame);
+            }
             UciData::IsReady => Uci::output("readyok"),
-            UciData::Go(time) => self.search.send(SearchControl::Start(time)),
+            UciData::Go(mut params, search_moves) => {
+                let searched = !search_moves.is_empty();
+                let (moves, invalid) = self.find_moves(search_moves);
+                for m in invalid {
+                    Uci::output_err(format!("Searchmove {m} is not legal, ignored."));
+                }
+                if searched && moves.is_empty() {
+                    Uci::output_err("No legal searchmoves, all the moves are searched.");
+                }
+                params.search_moves = moves;
+                self.search.send(SearchControl::Start(params));
+            }
+            UciData::PonderHit => self.search.send(SearchControl::PonderHit),
             UciData::Position(fen, moves) => {
                 self.setup_position(&fen, moves);
             }
@@ -54,7 +70,8 @@ impl Engine {
                         .mg
                         .get_all_legal_moves(&self.board.lock().unwrap(), false);
                     if s.contains("sort") {
-                        moves.give_scores(None, None, None);
+                        let board = self.board.lock().unwrap();
+                        moves.give_scores(None, None, None, None, Some((&self.mg, &board)));
                     }
                     for i in 0..moves.len() {
                         println!("{:?}", moves.nth(i));
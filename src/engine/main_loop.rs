@@ -37,7 +37,8 @@ impl Engine {
 
     fn search_report(info: &SearchResult) {
         match info {
-            SearchResult::BestMove(m) => Uci::output(format!("bestmove {m}")),
+            SearchResult::BestMove(m, Some(p)) => Uci::output(format!("bestmove {m} ponder {p}")),
+            SearchResult::BestMove(m, None) => Uci::output(format!("bestmove {m}")),
             SearchResult::Error => Uci::output_err("Something went wrong with the search"),
         }
     }
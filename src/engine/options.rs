@@ -27,6 +27,11 @@ impl UciType for u128 {
         String::from("spin")
     }
 }
+impl UciType for i16 {
+    fn uci_type() -> String {
+        String::from("spin")
+    }
+}
 
 // this simple macro extracts a value from a string given the type
 // and it's used to parse option values into rust types
@@ -45,23 +50,44 @@ macro_rules! extract_val {
             Err(())
         }
     }};
+    (i16, $s:expr) => {{
+        if let Ok(val) = $s.parse::<i16>() {
+            Ok(val)
+        } else {
+            Err(())
+        }
+    }};
     (bool, $s:expr) => {{
         Ok::<bool, ()>($s.to_lowercase() == "true")
     }};
 }
 
+// the name shown to the gui is the one in camel case, unless a different one is specified
+macro_rules! uci_name {
+    ($camel_name:ident) => {
+        stringify!($camel_name)
+    };
+    ($camel_name:ident, $uci_name:literal) => {
+        $uci_name
+    };
+}
+
 /* Options are set with the use of this macro, which takes in:
 *  - name of the option in CamelCase and snake_case: the name in camel case is what is shown to the
 *                                                    gui and is used to set the option
+*  - optional: name shown to the gui in parentheses after the camel case one, for names that
+*              are not valid identifiers (i.e. containing spaces)
 *  - type in rust for the option
 *  - default value
 *  - optional: closure for extra steps to perform when setting the options
 *              the closure can be adapted to have more arguments, every options will only use the
 *              needed ones
+*  - optional: min and max values of a spin option after a semicolon, they are shown to the gui
+*              and the values out of the range are clamped to it
 * With this arguments macro magic will do the rest and set up everything needed. Yay!
 */
 macro_rules! define_options {
-    {$($camel_name:ident, $snake_name:ident, $type:tt, $default:literal  $(,$extra:expr)? $(,$extra_uci:literal)?)
+    {$($camel_name:ident $(($uci_name:literal))?, $snake_name:ident, $type:tt, $default:literal  $(,$extra:expr)? $(;min $min:literal max $max:literal)?)
         *} => {
         // enum obtained from parsing uci and containing the value to set
         #[derive(Debug, PartialEq)]
@@ -71,7 +97,7 @@ macro_rules! define_options {
 
 
         // struct in the engine that hold the current option values
-        #[derive(Debug)]
+        #[derive(Debug, Clone)]
         pub struct Options {
             $(pub $snake_name: $type,)*
         }
@@ -87,7 +113,7 @@ macro_rules! define_options {
         // when the uci command is called, the option names and their types are listed to the gui
         impl Uci {
             pub fn show_options() {
-                $(print!("option name {} type {} default {}",stringify!($camel_name),<$type>::uci_type(),$default);$(print!(" {}", $extra_uci);)? println!();)*
+                $(print!("option name {} type {} default {}",uci_name!($camel_name $(,$uci_name)?),<$type>::uci_type(),$default);$(print!(" min {} max {}", $min, $max);)? println!();)*
             }
         }
 
@@ -95,7 +121,7 @@ macro_rules! define_options {
         impl EngineOption {
             pub fn from_string(name: &str, val: &str) -> Result<Self,()> {
                 match name {
-                    $(stringify!($camel_name) => {
+                    $(uci_name!($camel_name $(,$uci_name)?) => {
                         if let Ok(val) = extract_val!($type, val) {
                             Ok(Self::$camel_name(val))
                         } else {
@@ -113,6 +139,7 @@ macro_rules! define_options {
                 let mut opts = self.options.lock().expect(ErrFatal::LOCK);
                 match opt {
                     $($camel_name(val) => {
+                        $(let val = val.clamp($min, $max);)?
                         opts.$snake_name = val;
                         // the extra block is optional and contains actions that need to be
                         // performed when the option is set (i.e. resizing the tt)
@@ -129,8 +156,20 @@ macro_rules! define_options {
 
 // This is the actual call to the macro, to add a new option simply add it here
 define_options! {
-    Hash,hash_size,usize,128,|tt:&mut MutexGuard<TT<SearchData>>,val| {tt.resize(val);},"min 1 max 32768"
+    Hash,hash_size,usize,128,|tt:&mut MutexGuard<TT<SearchData>>,val| {tt.resize(val);};min 1 max 32768
     EarlyStop,early_stop,bool,true
     DbgUnicode,dbg_unicode,bool,true
     Overhead,move_overhead,u128,200
+    RfpMargin,rfp_margin,i16,80;min 0 max 1000
+    FutilityMargin,futility_margin,i16,120;min 0 max 1000
+    RazorMargin,razor_margin,i16,300;min 0 max 1000
+    QsChecks,qs_checks,bool,false
+    MultiPV,multi_pv,usize,1;min 1 max 218
+    Threads,threads,usize,1;min 1 max 512
+    Ponder,ponder,bool,false
+    NodesTime,nodes_time,u128,0;min 0 max 10000
+    SkillLevel("Skill Level"),skill_level,usize,20;min 0 max 20
+    LimitStrength("UCI_LimitStrength"),limit_strength,bool,false
+    Elo("UCI_Elo"),elo,usize,1500;min 800 max 2400
+    Contempt,contempt,i16,0;min -100 max 100
 }
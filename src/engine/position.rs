@@ -56,6 +56,9 @@ impl TryFrom<String> for SmallMove {
 
     fn try_from(s: String) -> Result<Self, Self::Error> {
         let chars: Vec<char> = s.chars().collect();
+        if !(4..=5).contains(&chars.len()) {
+            return Err(());
+        }
         let mut promotion: Piece = Pieces::NONE;
         let from = square_by_name(&chars[0..2].iter().collect::<String>())?;
         let to = square_by_name(&chars[2..4].iter().collect::<String>())?;
@@ -73,12 +76,15 @@ impl TryFrom<String> for SmallMove {
 }
 impl Into<String> for SmallMove {
     fn into(self) -> String {
+        // without promotion the piece char is a space
         format!(
             "{}{}{}",
             SQUARE_NAMES[self.from],
             SQUARE_NAMES[self.to],
             PieceNames::CHAR_LOWERCASE[self.promotion]
         )
+        .trim()
+        .to_string()
     }
 }
 impl PartialEq<Move> for SmallMove {
@@ -99,6 +105,38 @@ impl Engine {
         Ok(moves)
     }
 
+    // legal moves in the current position from the gui strings, and the strings that are not
+    // legal moves
+    pub fn find_moves(&self, strings: Vec<String>) -> (Vec<Move>, Vec<String>) {
+        let mut board = self.board.lock().expect(ErrFatal::LOCK);
+        let pseudo_legal = self.mg.get_all_legal_moves(&board, false);
+
+        let mut moves = Vec::with_capacity(strings.len());
+        let mut invalid = Vec::new();
+        for s in strings {
+            let Ok(small_move) = SmallMove::try_from(s.clone()) else {
+                invalid.push(s);
+                continue;
+            };
+            let legal = pseudo_legal
+                .iter()
+                .map(|ext| ext.m)
+                .find(|&m| small_move == m)
+                .filter(|&m| {
+                    let legal = board.make_move(m, &self.mg);
+                    if legal {
+                        board.unmake();
+                    }
+                    legal
+                });
+            match legal {
+                Some(m) => moves.push(m),
+                None => invalid.push(s),
+            }
+        }
+        (moves, invalid)
+    }
+
     fn play_moves(
         board: &mut Board,
         mg: &MoveGenerator,
@@ -1,3 +1,8 @@
+use std::{
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
 use crate::{defs::ZobristHash, eval::defs::Eval, moves::defs::Move};
 
 const BUCKET_ENTIRES: usize = 4;
@@ -91,57 +96,121 @@ impl TTData for SearchData {
     fn depth(&self) -> u8 {
         self.depth
     }
+
+    // bits: 0-31 best move, 32-47 eval, 48-55 depth, 56-57 eval type
+    fn data(&self) -> u64 {
+        let eval_type: u64 = match self.eval_type {
+            EvalType::Exact => 0,
+            EvalType::Alpha => 1,
+            EvalType::Beta => 2,
+        };
+        u64::from(self.best_move.data)
+            | u64::from(self.eval as u16) << 32
+            | u64::from(self.depth) << 48
+            | eval_type << 56
+    }
+
+    fn from_data(key: u64, data: u64) -> Self {
+        let eval_type = match (data >> 56) & 3 {
+            1 => EvalType::Alpha,
+            2 => EvalType::Beta,
+            _ => EvalType::Exact,
+        };
+        SearchData {
+            best_move: Move { data: data as u32 },
+            depth: (data >> 48) as u8,
+            eval: (data >> 32) as u16 as i16,
+            eval_type,
+            zobrist_hash: key,
+        }
+    }
 }
 
 pub trait TTData {
     fn key(&self) -> u64;
     fn depth(&self) -> u8;
+    // everything but the key packed in 64 bits, and the entry rebuilt from it
+    fn data(&self) -> u64;
+    fn from_data(key: u64, data: u64) -> Self;
+}
+
+// An entry stores its data packed in 64 bits, and the key xored with the data. The table is shared
+// by all the search threads without locks: when two threads write the same entry at the same time
+// the key and the data can come from different writes, so the key doesn't match anymore and the
+// corrupted entry is never returned
+#[derive(Default)]
+struct Entry {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Entry {
+    // the entry of a never used slot has key 0
+    fn load<T: TTData>(&self) -> T {
+        let data = self.data.load(Ordering::Relaxed);
+        let key = self.key.load(Ordering::Relaxed) ^ data;
+        T::from_data(key, data)
+    }
+
+    fn store<T: TTData>(&self, entry: T) {
+        let data = entry.data();
+        self.key.store(entry.key() ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
 }
 
-// A Bucket contains BUCKET_SIZE entires that would be mapped to the same index in the tt
-#[derive(Clone, Copy, Default)]
-pub struct Bucket<T: TTData + Default + Copy + Clone> {
-    data: [T; BUCKET_ENTIRES],
+// A Bucket contains BUCKET_SIZE entires that would be mapped to the same index in the tt, and
+// fills a cache line
+#[derive(Default)]
+#[repr(align(64))]
+pub struct Bucket {
+    data: [Entry; BUCKET_ENTIRES],
 }
 
-impl<T: TTData + Default + Copy + Clone> Bucket<T> {
+impl Bucket {
     // insert the data in the bucket and return whether the count of used entries needs to be increased.
     // If the data is not inserted there was higher quality data in the bucket
-    fn insert(&mut self, data: T) -> bool {
+    fn insert<T: TTData>(&self, data: T) -> bool {
         let mut min_priority = data.depth();
-        let mut min_priority_i: Option<usize> = None;
+        let mut min_priority_entry: Option<T> = None;
+        let mut min_priority_i = 0;
 
         // search for the entry with the smallest depth that will be replaced
         for (i, entry) in self.data.iter().enumerate() {
+            let entry: T = entry.load();
             if entry.depth() <= min_priority {
                 min_priority = entry.depth();
-                min_priority_i = Some(i);
+                min_priority_entry = Some(entry);
+                min_priority_i = i;
             }
         }
 
         // if all entries had higher depth than the new data's then it doesn't get inserted
-        if let Some(i) = min_priority_i {
+        if let Some(entry) = min_priority_entry {
             // if the hash is 0 the entry was never used so the counter has to be increased
-            let new = self.data[i].key() == 0;
+            let new = entry.key() == 0;
 
-            self.data[i] = data;
+            self.data[min_priority_i].store(data);
 
             return new;
         }
         false
     }
 
-    fn get(&self, key: u64) -> Option<T> {
-        self.data.into_iter().find(|entry| entry.key() == key)
+    fn get<T: TTData>(&self, key: u64) -> Option<T> {
+        (self.data.iter())
+            .map(|entry| entry.load::<T>())
+            .find(|entry| entry.key() == key)
     }
 }
 
 pub struct TT<T: TTData + Default + Copy + Clone> {
-    data: Vec<Bucket<T>>,
+    data: Vec<Bucket>,
     megabytes: usize,
     total_entries: usize,
     total_buckets: usize,
-    used_entries: usize,
+    used_entries: AtomicUsize,
+    data_type: PhantomData<T>,
 }
 
 impl<T: TTData + Default + Copy + Clone> TT<T> {
@@ -149,28 +218,34 @@ impl<T: TTData + Default + Copy + Clone> TT<T> {
         let (total_buckets, total_entries) = Self::calculate_sizes(megabytes);
         TT {
             megabytes,
-            data: vec![Bucket::default(); total_buckets],
+            data: Self::empty_buckets(total_buckets),
             total_entries,
             total_buckets,
-            used_entries: 0,
+            used_entries: AtomicUsize::new(0),
+            data_type: PhantomData,
         }
     }
 
     pub fn resize(&mut self, megabytes: usize) {
         let (total_buckets, total_entries) = Self::calculate_sizes(megabytes);
-        self.data = vec![Bucket::default(); total_buckets];
+        self.data = Self::empty_buckets(total_buckets);
         self.megabytes = megabytes;
-        self.used_entries = 0;
+        self.used_entries = AtomicUsize::new(0);
         self.total_buckets = total_buckets;
         self.total_entries = total_entries;
     }
 
-    pub fn insert(&mut self, data: T) {
+    pub fn clear(&mut self) {
+        self.data = Self::empty_buckets(self.total_buckets);
+        self.used_entries = AtomicUsize::new(0);
+    }
+
+    pub fn insert(&self, data: T) {
         let index = self.calculate_index(data.key());
         debug_assert!(index < self.total_buckets);
         let new_entry = self.data[index].insert(data);
         if new_entry {
-            self.used_entries += 1;
+            self.used_entries.fetch_add(1, Ordering::Relaxed);
         }
     }
 
@@ -187,15 +262,20 @@ impl<T: TTData + Default + Copy + Clone> TT<T> {
         (key as usize) % self.total_buckets
     }
 
+    fn empty_buckets(total_buckets: usize) -> Vec<Bucket> {
+        (0..total_buckets).map(|_| Bucket::default()).collect()
+    }
+
     fn calculate_sizes(megabytes: usize) -> (usize, usize) {
-        let bucket_size = std::mem::size_of::<Bucket<T>>();
+        let bucket_size = std::mem::size_of::<Bucket>();
         let buckets = MB / bucket_size * megabytes;
         let entries = buckets * BUCKET_ENTIRES;
         (buckets, entries)
     }
 
     pub fn hash_full(&self) -> u16 {
-        let permil = (self.used_entries as f64 / self.total_entries as f64) * 1000f64;
+        let used_entries = self.used_entries.load(Ordering::Relaxed);
+        let permil = (used_entries as f64 / self.total_entries as f64) * 1000f64;
         permil.round() as u16
     }
 }
@@ -220,8 +300,16 @@ mod tests {
             fn depth(&self) -> u8 {
                 3
             }
+
+            fn data(&self) -> u64 {
+                self.content
+            }
+
+            fn from_data(key: u64, content: u64) -> Self {
+                Self { content, key }
+            }
         }
-        let mut tt: TT<DbgData> = TT::new(16);
+        let tt: TT<DbgData> = TT::new(16);
         let data_in = DbgData {
             content: 392,
             key: 32,
@@ -234,7 +322,7 @@ mod tests {
 
     #[test]
     fn tt_search_test() {
-        let mut tt: TT<SearchData> = TT::new(32);
+        let tt: TT<SearchData> = TT::new(32);
         let mut b = Board::new();
         b.read_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
             .unwrap();
@@ -252,4 +340,20 @@ mod tests {
         assert_eq!(data_in, data_out.unwrap());
         dbg!(data_out.unwrap().get_values(Eval::INF, -Eval::INF, 3, 1));
     }
+
+    #[test]
+    fn tt_torn_entry() {
+        let bucket = Bucket::default();
+        let first = SearchData::new(Move::default(), 5, 0, 120, EvalType::Exact, 7);
+        let second = SearchData::new(Move::default(), 3, 0, -40, EvalType::Beta, 9);
+        bucket.insert(first);
+        assert_eq!(bucket.get(first.zobrist_hash), Some(first));
+
+        // another thread wrote only the data of a different entry in the meantime
+        for entry in &bucket.data {
+            entry.data.store(second.data(), Ordering::Relaxed);
+        }
+        assert_eq!(bucket.get::<SearchData>(first.zobrist_hash), None);
+        assert_eq!(bucket.get::<SearchData>(second.zobrist_hash), None);
+    }
 }
@@ -3,8 +3,10 @@ mod init_moves;
 mod legalmoves;
 mod list;
 pub mod magics;
+mod see;
 
 use self::list::MoveList;
+pub use self::list::QuietHeuristics;
 use crate::{
     board::Board,
     defs::{Bitboard, Colors, NrOf},
@@ -312,7 +312,7 @@ impl MoveGenerator {
         }
     }
 
-    fn get_bb_from_magics(&self, sq: Square, blocker: Bitboard, piece: Piece) -> Bitboard {
+    pub fn get_bb_from_magics(&self, sq: Square, blocker: Bitboard, piece: Piece) -> Bitboard {
         match piece {
             Pieces::ROOK => {
                 let rook_idx = ROOK_MAGICS[sq]
@@ -1,14 +1,18 @@
-use super::defs::{Move, MoveType};
+use super::{
+    defs::{Move, MoveType},
+    MoveGenerator,
+};
 use crate::{
-    defs::{Color, MAX_LEGAL_MOVES, PIECE_VALUES},
-    search::defs::HistoryHeuristic,
+    board::Board,
+    defs::{NrOf, MAX_LEGAL_MOVES, PIECE_VALUES},
+    search::history::{PieceToCaptureHistory, PieceToHistory},
 };
 
-/// contains a [`Move`] and an u16 that represents the move's score used for move ordering
+/// contains a [`Move`] and an i32 that represents the move's score used for move ordering
 #[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub struct ExtMove {
     pub m: Move,
-    s: u16,
+    s: i32,
 }
 impl PartialOrd for ExtMove {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
@@ -41,14 +45,33 @@ impl MoveList {
     }
 }
 
-/// contains the constants for scores used in move ordering
+/// the heuristics collected by the search to order the quiet moves of a node
+pub struct QuietHeuristics<'a> {
+    pub killers: &'a [Move; 2],
+    pub countermove: Move, // refutation of the previous move
+    pub history: &'a [[i16; NrOf::SQUARES]; NrOf::SQUARES], // for the side to move
+    pub continuations: [Option<&'a PieceToHistory>; 2], // following the moves one and two plies before
+}
+
+// the victim value is multiplied so that the attacker only reorders the captures of the same piece.
+// The capture history can reach a quarter of MAX_HISTORY, so it also reorders the captures of
+// pieces with close values (knight and bishop, or even rook and minor piece)
+const MVV_WEIGHT: i32 = 16;
+const CAPTURE_HISTORY_DIVISOR: i32 = 4;
+
+/// contains the constants for scores used in move ordering, the quiet moves get a score between
+/// CASTLING and BAD_CAPTURE from the sum of the history tables
 struct MoveOrdering;
 impl MoveOrdering {
-    const TT: u16 = u16::MAX;
-    const PROMOTION: u16 = 15000;
-    const CAPTURE: u16 = 10000;
-    const KILLER: u16 = 9000;
-    const CASTLING: u16 = 8000;
+    const PV: i32 = i32::MAX;
+    const TT: i32 = i32::MAX - 1;
+    const PROMOTION: i32 = 400_000;
+    const CAPTURE: i32 = 300_000;
+    const KILLER: i32 = 200_000;
+    const COUNTERMOVE: i32 = 190_000;
+    const CASTLING: i32 = 100_000;
+    const QUIET: i32 = 0;
+    const BAD_CAPTURE: i32 = -100_000;
 }
 
 /// implement the logic for giving scores to the moves according to an heuristic
@@ -83,40 +106,65 @@ impl MoveList {
     in [`MoveOrdering`].
 
     Uses the following heuristics in order:
+    - PV move from the previous iteration
     - TT move
     - Promotions
-    - Captures ordered by MVV
+    - Captures that don't lose material ordered by MVV-LVA and capture history
     - Quiet moves from killer heuristics
+    - Countermove of the previous move
     - Castling
-    - Quiet moves ordered with history heuristic
+    - Quiet moves ordered with history and continuation history heuristics
+    - Captures that lose material according to Static Exchange Evaluation
+
+    If the board is not given all the captures are considered good.
     */
     pub fn give_scores(
         &mut self,
+        pv_move: Option<Move>,
         tt_move: Option<Move>,
-        killer_moves: Option<&[Move; 2]>,
-        history: Option<(&HistoryHeuristic, Color)>,
+        quiet: Option<QuietHeuristics>,
+        capture_history: Option<&PieceToCaptureHistory>,
+        see: Option<(&MoveGenerator, &Board)>,
     ) {
-        //TODO: PV first from previous iterative depennig
-        //TODO: Static Exchange Evaluation
         for i in 0..self.len {
             let curr = &mut self.moves[i];
+            // the move is on the pv of the previous iteration, it's likely to be the best again
+            if pv_move.is_some_and(|m| curr.m == m) {
+                curr.s = MoveOrdering::PV;
+                continue;
+            }
             // the current move is the tt_move so it gets scored as best
             if tt_move.is_some_and(|m| curr.m == m) {
                 curr.s = MoveOrdering::TT;
                 continue;
             }
-            if let Some(killers) = killer_moves {
-                let killer_index = killers.iter().position(|&m| m == curr.m);
+            if let Some(q) = &quiet {
+                let killer_index = q.killers.iter().position(|&m| m == curr.m);
                 if let Some(i) = killer_index {
-                    curr.s = MoveOrdering::KILLER - (i as u16);
+                    curr.s = MoveOrdering::KILLER - (i as i32);
+                    continue;
+                }
+                if curr.m == q.countermove {
+                    curr.s = MoveOrdering::COUNTERMOVE;
                     continue;
                 }
             }
 
             if curr.m.move_type() == MoveType::Capture {
-                curr.s += MoveOrdering::CAPTURE;
-                // order by Most Valuable Victim (higher score for better victim)
-                curr.s += PIECE_VALUES[curr.m.captured_piece()];
+                let losing = see.is_some_and(|(mg, board)| !mg.see(board, curr.m, 0));
+                curr.s += if losing {
+                    MoveOrdering::BAD_CAPTURE
+                } else {
+                    MoveOrdering::CAPTURE
+                };
+                // order by Most Valuable Victim (higher score for better victim) and then by
+                // Least Valuable Attacker
+                let (piece, captured) = (curr.m.piece(), curr.m.captured_piece());
+                curr.s +=
+                    MVV_WEIGHT * i32::from(PIECE_VALUES[captured]) - i32::from(PIECE_VALUES[piece]);
+                if let Some(h) = capture_history {
+                    curr.s += i32::from(h[piece][curr.m.to()][captured]) / CAPTURE_HISTORY_DIVISOR;
+                }
             }
             if curr.m.is_castling() {
                 curr.s += MoveOrdering::CASTLING;
@@ -127,10 +175,12 @@ impl MoveList {
                 continue;
             }
             if curr.m.move_type() == MoveType::Quiet {
-                if let Some((history, color)) = history {
-                    curr.s += history[color][curr.m.from()][curr.m.to()];
-                    if curr.s >= MoveOrdering::CASTLING {
-                        curr.s = MoveOrdering::CASTLING - 1;
+                curr.s = MoveOrdering::QUIET;
+                if let Some(q) = &quiet {
+                    let (piece, to) = (curr.m.piece(), curr.m.to());
+                    curr.s += i32::from(q.history[curr.m.from()][to]);
+                    for h in q.continuations.iter().flatten() {
+                        curr.s += i32::from(h[piece][to]);
                     }
                 }
             }
@@ -0,0 +1,186 @@
+use crate::{
+    board::{defs::Pieces, Board},
+    defs::{Bitboard, Colors, Piece, Square, PIECE_VALUES},
+};
+
+use super::{defs::Move, MoveGenerator};
+
+// the pieces are tried as attackers starting from the least valuable
+const ATTACKER_ORDER: [Piece; 5] = [
+    Pieces::PAWN,
+    Pieces::KNIGHT,
+    Pieces::BISHOP,
+    Pieces::ROOK,
+    Pieces::QUEEN,
+];
+
+fn see_value(piece: Piece) -> i16 {
+    if piece == Pieces::NONE {
+        0
+    } else {
+        PIECE_VALUES[piece] as i16
+    }
+}
+
+impl MoveGenerator {
+    // all the pieces of both colors that attack the square, considering only the occupied squares
+    // as blockers for the sliding pieces
+    pub fn attackers_to(&self, board: &Board, sq: Square, occupied: Bitboard) -> Bitboard {
+        let white = board.piece_bbs[Colors::WHITE];
+        let black = board.piece_bbs[Colors::BLACK];
+        let diagonal = white[Pieces::BISHOP]
+            | white[Pieces::QUEEN]
+            | black[Pieces::BISHOP]
+            | black[Pieces::QUEEN];
+        let straight =
+            white[Pieces::ROOK] | white[Pieces::QUEEN] | black[Pieces::ROOK] | black[Pieces::QUEEN];
+
+        (self.pawn_capture[Colors::BLACK][sq] & white[Pieces::PAWN])
+            | (self.pawn_capture[Colors::WHITE][sq] & black[Pieces::PAWN])
+            | (self.knight[sq] & (white[Pieces::KNIGHT] | black[Pieces::KNIGHT]))
+            | (self.king[sq] & (white[Pieces::KING] | black[Pieces::KING]))
+            | (self.get_bb_from_magics(sq, occupied, Pieces::BISHOP) & diagonal)
+            | (self.get_bb_from_magics(sq, occupied, Pieces::ROOK) & straight)
+    }
+
+    /**
+    Static Exchange Evaluation: returns whether the material balance after all the captures
+    on the destination square of the move is at least the threshold, assuming both sides
+    always recapture with their least valuable piece and can stop capturing when it's not
+    convenient anymore.
+
+    Sliding pieces behind the capturing ones (x-rays) join the exchange when the squares in
+    front of them are cleared. Castling and promotions are not evaluated and count as even.
+    */
+    pub fn see(&self, board: &Board, m: Move, threshold: i16) -> bool {
+        if m.is_castling() || m.is_promotion() {
+            return threshold <= 0;
+        }
+        let from = m.from();
+        let to = m.to();
+
+        // the balance if the opponent doesn't recapture
+        let mut swap = see_value(m.captured_piece()) - threshold;
+        if swap < 0 {
+            return false;
+        }
+        // the balance if the opponent recaptures and the exchange stops there
+        swap = see_value(m.piece()) - swap;
+        if swap <= 0 {
+            return true;
+        }
+
+        let mut occupied = (board.color_bbs[Colors::WHITE] | board.color_bbs[Colors::BLACK])
+            ^ (1 << from)
+            ^ (1 << to);
+        if m.is_en_passant() {
+            let captured_sq = if board.state.active_color == Colors::WHITE {
+                to - 8
+            } else {
+                to + 8
+            };
+            occupied ^= 1 << captured_sq;
+        }
+
+        let mut color = board.state.active_color;
+        let mut attackers = self.attackers_to(board, to, occupied);
+        // whether the side that made the move is winning the exchange
+        let mut res = true;
+
+        loop {
+            color ^= 1;
+            attackers &= occupied;
+            let color_attackers = attackers & board.color_bbs[color];
+            if color_attackers == 0 {
+                break;
+            }
+            res = !res;
+
+            let pieces = board.piece_bbs[color];
+            let Some(piece) = ATTACKER_ORDER
+                .into_iter()
+                .find(|&p| pieces[p] & color_attackers > 0)
+            else {
+                // only the king can capture: it's only possible if the square is not defended
+                let defended = attackers & board.color_bbs[color ^ 1] > 0;
+                return if defended { !res } else { res };
+            };
+
+            swap = see_value(piece) - swap;
+            if swap < i16::from(res) {
+                break;
+            }
+
+            // remove the capturing piece and add the sliding pieces that were behind it
+            occupied ^= 1 << (pieces[piece] & color_attackers).trailing_zeros();
+            if matches!(piece, Pieces::PAWN | Pieces::BISHOP | Pieces::QUEEN) {
+                attackers |= self.get_bb_from_magics(to, occupied, Pieces::BISHOP)
+                    & (board.piece_bbs[Colors::WHITE][Pieces::BISHOP]
+                        | board.piece_bbs[Colors::WHITE][Pieces::QUEEN]
+                        | board.piece_bbs[Colors::BLACK][Pieces::BISHOP]
+                        | board.piece_bbs[Colors::BLACK][Pieces::QUEEN]);
+            }
+            if matches!(piece, Pieces::ROOK | Pieces::QUEEN) {
+                attackers |= self.get_bb_from_magics(to, occupied, Pieces::ROOK)
+                    & (board.piece_bbs[Colors::WHITE][Pieces::ROOK]
+                        | board.piece_bbs[Colors::WHITE][Pieces::QUEEN]
+                        | board.piece_bbs[Colors::BLACK][Pieces::ROOK]
+                        | board.piece_bbs[Colors::BLACK][Pieces::QUEEN]);
+            }
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::{defs::square_by_name, Board},
+        moves::MoveGenerator,
+    };
+
+    #[test]
+    fn static_exchange_evaluation() {
+        let mut mg = MoveGenerator::default();
+        mg.init();
+        let mut b = Board::new();
+
+        // fen, move, the highest threshold for which the exchange is not losing
+        let positions = [
+            // undefended pawn
+            (
+                "1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1",
+                "e1",
+                "e5",
+                100,
+            ),
+            // knight for pawn with x-rays on both sides
+            (
+                "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+                "d3",
+                "e5",
+                -220,
+            ),
+            // queen takes a pawn defended by a pawn
+            ("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1", "d1", "d5", -800),
+            // rook takes a rook defended only by the king
+            ("3rk3/8/8/8/8/8/8/3RK3 w - - 0 1", "d1", "d8", 0),
+            // the king can't recapture a defended piece
+            ("3rk3/8/8/8/8/8/3R4/3RK3 w - - 0 1", "d2", "d8", 500),
+        ];
+
+        for (fen, from, to, value) in positions {
+            b.read_fen(fen).unwrap();
+            let (from, to) = (square_by_name(from).unwrap(), square_by_name(to).unwrap());
+            let moves = mg.get_all_legal_moves(&b, true);
+            let m = moves
+                .iter()
+                .map(|ext| ext.m)
+                .find(|m| m.from() == from && m.to() == to)
+                .unwrap();
+            assert!(mg.see(&b, m, value), "{fen}");
+            assert!(!mg.see(&b, m, value + 1), "{fen}");
+        }
+    }
+}
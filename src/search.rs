@@ -1,12 +1,14 @@
 mod alpha_beta;
 pub mod defs;
 mod draw;
+pub mod history;
 mod iter_deep;
 mod quiescence;
+mod skill;
 mod time;
 
 use std::{
-    sync::{Arc, Mutex},
+    sync::{atomic::Ordering, Arc, Mutex},
     thread::{self, JoinHandle},
 };
 
@@ -14,16 +16,22 @@ use crossbeam_channel::Sender;
 
 use crate::{
     board::Board,
-    defs::{Colors, ErrFatal, Info, NrOf},
+    defs::{ErrFatal, Info},
     engine::{
         options::Options,
-        transposition::{SearchData, TT},
+        transposition::{EvalType, SearchData, TT},
     },
     moves::{defs::Move, MoveGenerator},
     search::defs::SearchTerminate,
+    uci::Uci,
 };
 
-use self::defs::{SearchControl, SearchInfo, SearchRefs, SearchTime, MAX_PLY};
+use self::defs::{
+    PlyData, PvTable, SearchControl, SearchInfo, SearchRefs, SearchResult, SharedInfo, MAIN_THREAD,
+    MAX_PLY,
+};
+use self::history::HistoryTables;
+use self::skill::SKILL_MULTI_PV;
 
 #[derive(Default)]
 pub struct Search {
@@ -44,40 +52,106 @@ impl Search {
 
         let h = thread::spawn(move || {
             let mut quit = false;
-            let mut stop = true;
+            // the move ordering tables of the main thread are kept for the whole game
+            let mut history = HistoryTables::new();
 
             while !quit {
                 let cmd = rx.recv().expect(ErrFatal::RX_RECV);
-                let mut search_time = SearchTime::Infinite;
+                let mut params = None;
                 match cmd {
-                    SearchControl::Start(time) => {
-                        stop = false;
-                        search_time = time;
-                    }
-                    SearchControl::Stop => stop = true,
+                    SearchControl::Start(p) => params = Some(p),
+                    SearchControl::NewGame => history.clear(),
                     SearchControl::Quit => quit = true,
+                    // no search is running
+                    SearchControl::Stop | SearchControl::PonderHit => (),
                 }
-                if !stop && !quit {
+                if let Some(params) = params {
                     let mut board = board.lock().expect(ErrFatal::LOCK);
-                    let mut tt = tt.lock().expect(ErrFatal::LOCK);
+                    let tt = tt.lock().expect(ErrFatal::LOCK);
+                    let mut options = options.lock().expect(ErrFatal::LOCK).clone();
+                    let mut limits = params.limits;
+                    // the searches with a node budget from the gui or a clock measured in nodes
+                    // are reproducible, and so must be the weakened move
+                    let fixed_seed = options.nodes_time > 0 || limits.nodes.is_some();
+
+                    // a weaker engine searches less and chooses among more lines
+                    let skill = Self::skill_level(&options);
+                    if let Some(level) = skill {
+                        Self::limit_strength(&mut limits, level);
+                        options.multi_pv = options.multi_pv.max(SKILL_MULTI_PV);
+                    }
+
+                    // Lazy SMP: the helper threads search their own copy of the board and only
+                    // share the tt with the main thread, which fills it with useful results
+                    let helpers = options.threads.max(1) - 1;
+                    let mut helper_boards = vec![board.clone(); helpers];
+                    let mut helper_infos: Vec<SearchInfo> =
+                        (0..helpers).map(|_| SearchInfo::default()).collect();
+                    let shared = SharedInfo::new(helpers + 1);
+                    let mut info = SearchInfo::default();
+
+                    // the values from the previous search are still useful but less reliable,
+                    // the helpers start from the main thread's tables
+                    history.age();
+                    let mut helper_history = vec![history.clone(); helpers];
 
                     let mut refs = SearchRefs {
                         board: &mut board,
-                        tt: &mut tt,
+                        tt: &tt,
                         killer_moves: [[Move::default(); 2]; MAX_PLY as usize],
-                        history_heuristic: [[[0; NrOf::SQUARES]; NrOf::SQUARES]; Colors::BOTH],
+                        stack: [PlyData::default(); MAX_PLY as usize + 1],
+                        pv_table: PvTable::new(),
+                        root_excluded: Vec::new(),
+                        search_moves: params.search_moves,
+                        history: &mut history,
                         mg: &mg,
-                        time_control: search_time,
+                        limits,
                         timer: None,
-                        info: &mut SearchInfo::default(),
+                        pondering: params.ponder,
+                        ponderhit_time: 0,
+                        info: &mut info,
                         terminate: SearchTerminate::Nothing,
                         report_tx: &report_tx,
                         control_rx: &rx,
-                        options: &options,
+                        options,
+                        thread_id: MAIN_THREAD,
+                        shared: &shared,
                     };
 
-                    let res = Self::iterative_deepening(&mut refs);
+                    thread::scope(|s| {
+                        let helpers = (helper_boards.iter_mut())
+                            .zip(helper_infos.iter_mut())
+                            .zip(helper_history.iter_mut());
+                        for (i, ((board, info), history)) in helpers.enumerate() {
+                            let mut helper_refs = refs.helper(i + 1, board, info, history);
+                            s.spawn(move || Self::iterative_deepening(&mut helper_refs));
+                        }
+                        Self::iterative_deepening(&mut refs);
+                        Self::wait_ponder_end(&mut refs);
+                        shared.stop.store(true, Ordering::Relaxed);
+                    });
                     quit = refs.terminate == SearchTerminate::Quit;
+
+                    // the gui was only told the main thread's pv, the one of a helper is reported
+                    // before playing its move
+                    let best_helper = Self::best_helper(refs.info, &helper_infos);
+                    if let (Some(helper), None) = (best_helper, skill) {
+                        refs.info.depth = helper.completed_depth;
+                        refs.info.seldepth = helper.seldepth;
+                        let (pv, eval) = (&helper.best_pv, helper.best_eval);
+                        Uci::search_info(&refs, pv, eval, EvalType::Exact, tt.hash_full(), 1);
+                    }
+
+                    // a weaker engine plays a different move every search, unless the search has
+                    // to be reproducible
+                    let seed = fixed_seed.then_some(refs.board.state.zobrist_hash);
+                    let res = match skill {
+                        Some(level) => Self::skill_result(&info.lines, level, seed),
+                        None => {
+                            let best = best_helper.unwrap_or(&info);
+                            Self::best_result(best, &mut board, &mg, &tt)
+                        }
+                    };
                     report_tx.send(Info::Search(res)).expect(ErrFatal::TX_SEND);
                 }
             }
@@ -87,6 +161,72 @@ impl Search {
         self.handle = Some(h);
     }
 
+    // The result of the thread that completed the deepest iteration is used, preferring the main
+    // thread. At equal depth a helper is chosen only if it found a better evaluation.
+    fn best_helper<'a>(main: &SearchInfo, helpers: &'a [SearchInfo]) -> Option<&'a SearchInfo> {
+        let mut best = (main.completed_depth, main.best_eval);
+        let mut best_helper = None;
+        for info in helpers.iter().filter(|info| !info.best_pv.is_empty()) {
+            if (info.completed_depth, info.best_eval) > best {
+                best = (info.completed_depth, info.best_eval);
+                best_helper = Some(info);
+            }
+        }
+        best_helper
+    }
+
+    fn best_result(
+        best: &SearchInfo,
+        board: &mut Board,
+        mg: &MoveGenerator,
+        tt: &TT<SearchData>,
+    ) -> SearchResult {
+        // the ponder move is the reply from the pv, or from the tt when the pv was cut short
+        match best.best_pv[..] {
+            [] => SearchResult::Error,
+            [m] => SearchResult::BestMove(m, Self::tt_ponder_move(board, mg, tt, m)),
+            [m, ponder, ..] => SearchResult::BestMove(m, Some(ponder)),
+        }
+    }
+
+    // the tt move of the position after the best move, if it is legal
+    fn tt_ponder_move(
+        board: &mut Board,
+        mg: &MoveGenerator,
+        tt: &TT<SearchData>,
+        best_move: Move,
+    ) -> Option<Move> {
+        if !board.make_move(best_move, mg) {
+            return None;
+        }
+        let ponder = tt
+            .get(board.state.zobrist_hash)
+            .map(|data| data.best_move)
+            .filter(|&m| {
+                let moves = mg.get_all_legal_moves(board, false);
+                let legal = moves.iter().any(|ext| ext.m == m) && board.make_move(m, mg);
+                if legal {
+                    board.unmake();
+                }
+                legal
+            });
+        board.unmake();
+        ponder
+    }
+
+    // the gui expects the bestmove only after stop or ponderhit, even if the search finished
+    // while pondering
+    fn wait_ponder_end(refs: &mut SearchRefs) {
+        while refs.pondering && !refs.stopped() {
+            match refs.control_rx.recv().expect(ErrFatal::RX_RECV) {
+                SearchControl::PonderHit => refs.pondering = false,
+                SearchControl::Stop => refs.terminate = SearchTerminate::Stop,
+                SearchControl::Quit => refs.terminate = SearchTerminate::Quit,
+                SearchControl::Start(_) | SearchControl::NewGame => (),
+            }
+        }
+    }
+
     pub fn send(&self, cmd: SearchControl) {
         match &self.control_tx {
             Some(tx) => tx.send(cmd).expect(ErrFatal::TX_SEND),
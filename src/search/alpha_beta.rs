@@ -1,31 +1,63 @@
+use std::sync::atomic::Ordering;
+
 use super::{
     defs::{SearchRefs, MAX_PLY},
+    history::HistoryTables,
     Search,
 };
 use crate::{
+    board::defs::{Pieces, Ranks, RANK_BBS, SQUARE_BBS},
+    defs::Colors,
     engine::transposition::{EvalType, SearchData},
     eval::{defs::Eval, evaluate},
-    moves::defs::{Move, MoveType},
+    moves::{
+        defs::{Move, MoveType},
+        QuietHeuristics,
+    },
     search::defs::{SearchControl, SearchTerminate},
+    uci::Uci,
 };
 
+const NULL_MOVE_MIN_DEPTH: u8 = 3;
+const NULL_MOVE_REDUCTION: u8 = 3;
+const NULL_MOVE_VERIFICATION_DEPTH: u8 = 12;
+
+const RFP_MAX_DEPTH: u8 = 6;
+const RAZOR_MAX_DEPTH: u8 = 2;
+const FUTILITY_MAX_DEPTH: u8 = 2;
+
+const LMR_MIN_DEPTH: u8 = 3;
+const LMR_MIN_MOVES: u8 = 3; // the first moves are never reduced
+const LMR_BASE: f32 = 0.75;
+const LMR_DIVISOR: f32 = 2.25;
+const LMR_GOOD_HISTORY: i16 = 4096;
+const LMP_MAX_DEPTH: u8 = 3;
+const LMP_BASE_MOVES: u8 = 3;
+
+const IIR_MIN_DEPTH: u8 = 4;
+
+// moves that can get a malus after a cutoff
+const MAX_QUIETS_PUNISHED: usize = 64;
+const MAX_CAPTURES_PUNISHED: usize = 32;
+
+const SINGULAR_MIN_DEPTH: u8 = 8;
+const SINGULAR_TT_DEPTH: u8 = 3; // how much shallower than the node the tt entry can be
+const SINGULAR_MARGIN: i16 = 5; // per ply of depth
+
+// the gui is told the root move being searched only in long searches, in milliseconds
+const CURRMOVE_DELAY: u128 = 3000;
+// interval between the info lines sent during an iteration, in milliseconds
+const INFO_INTERVAL: u128 = 1000;
+// nodes between two checks of the stop conditions
+const CHECK_INTERVAL: u64 = 1024;
+
 impl Search {
-    pub fn alpha_beta(
-        mut depth: u8,
-        mut alpha: i16,
-        beta: i16,
-        pv: &mut Vec<Move>,
-        refs: &mut SearchRefs,
-    ) -> i16 {
-        // check if the search has to stop
-        // only perform the check every 2048 nodes for efficency,
-        // since the calculation of the elapsed time is expensive
-        if refs.info.nodes & 2048 == 0 {
-            Self::check_termination(refs);
-        }
+    pub fn alpha_beta(mut depth: u8, mut alpha: i16, beta: i16, refs: &mut SearchRefs) -> i16 {
         let is_root = refs.info.ply == 0;
+        let ply = refs.info.ply as usize;
+        refs.pv_table.clear(ply);
 
-        if refs.stopped() || refs.info.ply > MAX_PLY {
+        if refs.stopped() || refs.info.ply >= MAX_PLY {
             return evaluate(refs.board);
         }
 
@@ -34,18 +66,30 @@ impl Search {
             refs.board.king_square(refs.board.state.active_color),
             refs.board.state.active_color ^ 1,
         );
-        if is_check {
-            // if we're in check we can't start quiescence search, so we need to increase depth
+        // the extensions on a path can't exceed the iteration depth, so that the search can't
+        // explode in positions where many moves are extended
+        let mut extensions = refs.stack[ply].extensions;
+        if is_check && extensions < refs.info.depth {
+            // extend the search when in check, so that the threats are not pushed over the horizon
             depth += 1;
+            extensions += 1;
         }
+        let can_extend = extensions < refs.info.depth;
         if depth == 0 {
-            return Self::quiescence_search(refs, alpha, beta, pv);
+            return Self::quiescence_search(refs, alpha, beta, refs.options.qs_checks);
         }
 
-        refs.info.nodes += 1;
+        Self::count_node(refs);
+
+        // pv nodes are searched with an open window, every other node with a null window
+        let pv_node = refs.stack[ply].pv_node;
+        // the node is being searched without this move to verify a singular extension
+        let excluded = refs.stack[ply].excluded;
+        let excluding = excluded != Move::default();
 
         // only try to load from the tt if it's not the first move
         let mut tt_move = None;
+        let mut tt_data = None;
         if !is_root {
             let mut tt_eval = None;
             // try to get value from the transposition table
@@ -53,52 +97,263 @@ impl Search {
                 let (eval, m) = data.get_values(alpha, beta, depth, refs.info.ply);
                 tt_eval = eval;
                 tt_move = Some(m);
+                tt_data = Some(data);
             }
-            if let Some(eval) = tt_eval {
+            // cutoffs are only taken in non pv nodes, so that the pv doesn't get truncated, the
+            // entry is also of no use when the tt move is excluded
+            if let Some(eval) = tt_eval.filter(|_| !pv_node && !excluding) {
                 return eval;
             }
         }
 
+        // the static evaluation can't be trusted when in check, since the position is not quiet
+        let static_eval = if is_check {
+            -Eval::INF
+        } else {
+            evaluate(refs.board)
+        };
+        // pruning based on the static evaluation is only safe when no mate score is involved
+        let can_prune = !pv_node
+            && !is_check
+            && !excluding
+            && alpha > -Eval::CHECKMATE_TRESHOLD
+            && beta < Eval::CHECKMATE_TRESHOLD;
+
+        if can_prune {
+            let depth_i16 = i16::from(depth);
+
+            // REVERSE FUTILITY PRUNING
+            // the static evaluation is so far above beta that even after losing the margin for
+            // every ply left the node would fail high
+            let rfp_margin = refs.options.rfp_margin;
+            if depth <= RFP_MAX_DEPTH
+                && static_eval.saturating_sub(rfp_margin.saturating_mul(depth_i16)) >= beta
+            {
+                return beta;
+            }
+
+            // RAZORING
+            // the static evaluation is so far below alpha that only captures could bring it back,
+            // so the quiescence search decides if the node fails low
+            let razor_margin = refs.options.razor_margin;
+            if depth <= RAZOR_MAX_DEPTH
+                && static_eval.saturating_add(razor_margin.saturating_mul(depth_i16)) < alpha
+            {
+                let eval = Self::quiescence_search(refs, alpha, alpha + 1, false);
+                if eval <= alpha {
+                    return alpha;
+                }
+            }
+
+            if Self::null_move_cutoff(depth, beta, static_eval, refs) {
+                return beta;
+            }
+        }
+
+        // FUTILITY PRUNING
+        // close to the horizon a quiet move is not expected to raise the static evaluation by
+        // more than the margin, if that's still below alpha quiet moves are not searched
+        let futility_margin = refs.options.futility_margin;
+        let futile = can_prune
+            && depth <= FUTILITY_MAX_DEPTH
+            && static_eval.saturating_add(futility_margin.saturating_mul(i16::from(depth)))
+                <= alpha;
+
+        // INTERNAL ITERATIVE REDUCTIONS
+        // a pv node without a tt move would be searched with poor move ordering, so it's searched
+        // one ply shallower: it's cheaper and the next iteration will find a tt move to try first
+        let no_tt_move = tt_move.is_none_or(|m| m == Move::default());
+        if pv_node && !is_root && !excluding && no_tt_move && depth >= IIR_MIN_DEPTH {
+            depth -= 1;
+        }
+
+        // SINGULAR EXTENSION
+        // when the tt move is much better than all the others, searched with a reduced depth and a
+        // window below the tt evaluation, it's the only good move and gets extended
+        let mut singular_move = None;
+        if let Some(data) = tt_data.filter(|data| {
+            !is_root
+                && !excluding
+                && depth >= SINGULAR_MIN_DEPTH
+                && data.depth + SINGULAR_TT_DEPTH >= depth
+                && data.eval_type != EvalType::Alpha
+                && data.eval.abs() < Eval::CHECKMATE_TRESHOLD
+                && can_extend
+        }) {
+            let singular_beta = data.eval - SINGULAR_MARGIN * i16::from(depth);
+            // the verification is a null window search, not a pv one
+            refs.stack[ply].excluded = data.best_move;
+            refs.stack[ply].pv_node = false;
+            let eval = Self::alpha_beta((depth - 1) / 2, singular_beta - 1, singular_beta, refs);
+            refs.stack[ply].pv_node = pv_node;
+            refs.stack[ply].excluded = Move::default();
+
+            if eval < singular_beta {
+                singular_move = Some(data.best_move);
+            } else if singular_beta >= beta && !pv_node {
+                // multi cut: even without the tt move the node fails high
+                return beta;
+            }
+        }
+
+        // the searches of the same node above could have left their pv
+        refs.pv_table.clear(ply);
+
         let mut legal_moves = 0;
         let mut eval_type = EvalType::Alpha;
 
         let mut best_eval = -Eval::INF;
         let mut best_move = Move::default();
-        let ply = refs.info.ply as usize;
+
+        let color = refs.board.state.active_color;
+        let prev_moves = Self::previous_moves(refs);
+        let countermove = prev_moves[0].map_or(Move::default(), |prev| {
+            refs.history.countermoves[color][prev.piece()][prev.to()]
+        });
 
         let mut moves = refs.mg.get_all_legal_moves(refs.board, false);
+        let quiet = QuietHeuristics {
+            killers: &refs.killer_moves[ply],
+            countermove,
+            history: &refs.history.butterfly[color],
+            continuations: [0, 1]
+                .map(|i| prev_moves[i].map(|prev| refs.history.continuation[i].get(color, prev))),
+        };
         moves.give_scores(
+            refs.previous_pv_move(),
             tt_move,
-            Some(&refs.killer_moves[ply]),
-            Some((&refs.history_heuristic, refs.board.state.active_color)),
+            Some(quiet),
+            Some(&refs.history.capture[color]),
+            Some((refs.mg, refs.board)),
         );
 
+        let last_move = refs.board.last_move().unwrap_or_default();
+        let mut quiet_moves = 0;
+        // quiet moves searched without a cutoff
+        let mut quiets_searched = [Move::default(); MAX_QUIETS_PUNISHED];
+        let mut quiets_searched_count = 0;
+        let mut captures_searched = [Move::default(); MAX_CAPTURES_PUNISHED];
+        let mut captures_searched_count = 0;
+
         for i in 0..moves.len() {
             let m = moves.nth(i);
+            if (is_root && !refs.is_root_searched(m)) || m == excluded {
+                continue;
+            }
+            let is_quiet = m.move_type() == MoveType::Quiet && !m.is_promotion();
+            let is_capture = m.move_type() == MoveType::Capture;
+
+            // LATE MOVE PRUNING
+            // quiet moves are ordered by how likely they are to be good, so at low depth the last
+            // ones can be skipped once enough of them have been searched
+            if is_quiet
+                && !pv_node
+                && !is_check
+                && depth <= LMP_MAX_DEPTH
+                && quiet_moves >= LMP_BASE_MOVES + depth * depth
+                && best_eval > -Eval::CHECKMATE_TRESHOLD
+            {
+                continue;
+            }
+
             let legal = refs.board.make_move(m, refs.mg);
             if !legal {
                 continue;
             }
+            let start_nodes = refs.info.nodes;
 
             legal_moves += 1;
+            if is_quiet {
+                quiet_moves += 1;
+            }
+            if is_root && refs.is_main() && refs.timer_elapsed() >= CURRMOVE_DELAY {
+                Uci::current_move(refs.info.depth, m, legal_moves);
+            }
+
+            let gives_check = is_quiet
+                && refs
+                    .mg
+                    .square_attacked(refs.board, refs.board.king_square(color ^ 1), color);
+
+            // at least one move is always searched so that mate and stalemate are detected
+            if futile && is_quiet && !gives_check && legal_moves > 1 {
+                refs.board.unmake();
+                continue;
+            }
+
+            refs.stack[ply].current_move = m;
             refs.info.ply += 1;
             if refs.info.ply >= refs.info.seldepth {
                 refs.info.seldepth = refs.info.ply;
             }
 
-            let mut node_pv = Vec::new();
+            // EXTENSIONS
+            // the singular move, recaptures on the pv and pawn pushes to the 7th rank are
+            // searched one ply deeper since the tactic they start could be cut off by the horizon
+            let mut extension = 0;
+            if can_extend {
+                let recapture = pv_node
+                    && is_capture
+                    && last_move.move_type() == MoveType::Capture
+                    && last_move.to() == m.to();
+                let seventh_rank = match color {
+                    Colors::WHITE => Ranks::R7,
+                    _ => Ranks::R2,
+                };
+                let pawn_push =
+                    m.piece() == Pieces::PAWN && SQUARE_BBS[m.to()] & RANK_BBS[seventh_rank] > 0;
+                extension = u8::from(singular_move == Some(m) || recapture || pawn_push);
+            }
+            refs.stack[ply + 1].extensions = extensions + extension;
+
+            // LATE MOVE REDUCTIONS
+            // quiet moves late in the list are unlikely to be good, so they are searched with
+            // a reduced depth, less so if there is a reason to think they might be interesting
+            let mut reduction = 0;
+            if is_quiet
+                && extension == 0
+                && !is_check
+                && depth >= LMR_MIN_DEPTH
+                && legal_moves > LMR_MIN_MOVES
+            {
+                let killer = refs.killer_moves[ply].contains(&m);
+                let good_history =
+                    refs.history.butterfly[color][m.from()][m.to()] >= LMR_GOOD_HISTORY;
 
-            let mut eval = 0;
-            // search if it's not a draw OR if we are at the root
-            // The is_root check is needed because otherwise the engine will evaluate as a draw
-            // any position were a draw by repetition can be reached
-            if !Self::is_draw(refs.board) || is_root {
-                eval = -Self::alpha_beta(depth - 1, -beta, -alpha, &mut node_pv, refs);
+                let mut r = Self::lmr_reduction(depth, legal_moves);
+                for less_reduced in [pv_node, killer, gives_check, good_history] {
+                    r = r.saturating_sub(u8::from(less_reduced));
+                }
+                // always leave at least one ply to search
+                reduction = r.min(depth - 2);
+            }
+
+            let mut eval = refs.draw_score(ply);
+            // only the draws by the rules are checked at the root, otherwise the engine would
+            // evaluate as a draw any move that goes back to a position of the game
+            let draw = if is_root {
+                Self::is_rule_draw(refs.board)
+            } else {
+                Self::is_draw(refs.board)
+            };
+            refs.pv_table.clear(ply + 1);
+            if !draw {
+                eval = Self::pvs(
+                    depth + extension,
+                    alpha,
+                    beta,
+                    legal_moves == 1,
+                    reduction,
+                    refs,
+                );
             }
 
             refs.board.unmake();
             refs.info.ply -= 1;
 
+            if is_root {
+                refs.info.add_root_nodes(m, refs.info.nodes - start_nodes);
+            }
             if eval > best_eval {
                 best_eval = eval;
                 best_move = m;
@@ -106,41 +361,73 @@ impl Search {
 
             // the move is too good for the opponent, stop searching
             if eval >= beta {
-                refs.tt.insert(SearchData::new(
-                    best_move,
-                    depth,
-                    refs.info.ply,
-                    beta,
-                    EvalType::Beta,
-                    refs.board.state.zobrist_hash,
-                ));
-                if m.move_type() == MoveType::Quiet {
+                if !excluding {
+                    refs.tt.insert(SearchData::new(
+                        best_move,
+                        depth,
+                        refs.info.ply,
+                        beta,
+                        EvalType::Beta,
+                        refs.board.state.zobrist_hash,
+                    ));
+                }
+                // the move that failed high at the root is shown to the gui while the window widens
+                if is_root {
+                    refs.pv_table.update(ply, m);
+                }
+                let bonus = HistoryTables::bonus(depth);
+                if is_quiet {
                     if refs.killer_moves[ply][0] != m {
                         refs.killer_moves[ply][1] = refs.killer_moves[ply][0];
                         refs.killer_moves[ply][0] = m;
                     }
-                    refs.history_heuristic[refs.board.state.active_color][m.from()][m.to()] += 1;
+                    if let Some(prev) = prev_moves[0] {
+                        refs.history.countermoves[color][prev.piece()][prev.to()] = m;
+                    }
+
+                    // the cutoff move is rewarded, the quiet moves searched before it punished
+                    refs.history.update_quiet(color, m, prev_moves, bonus);
+                    for &quiet in &quiets_searched[..quiets_searched_count] {
+                        refs.history.update_quiet(color, quiet, prev_moves, -bonus);
+                    }
+                } else if is_capture {
+                    refs.history.update_capture(color, m, bonus);
+                }
+                // the captures searched before failed to cause a cutoff, whatever the best move
+                for &capture in &captures_searched[..captures_searched_count] {
+                    refs.history.update_capture(color, capture, -bonus);
                 }
                 return beta;
             }
+            if is_quiet && quiets_searched_count < MAX_QUIETS_PUNISHED {
+                quiets_searched[quiets_searched_count] = m;
+                quiets_searched_count += 1;
+            }
+            if is_capture && captures_searched_count < MAX_CAPTURES_PUNISHED {
+                captures_searched[captures_searched_count] = m;
+                captures_searched_count += 1;
+            }
 
             // the move is great for us
             if eval > alpha {
                 eval_type = EvalType::Exact;
                 alpha = eval;
 
-                pv.clear();
-                pv.push(m);
-                pv.append(&mut node_pv);
+                refs.pv_table.update(ply, m);
             }
         }
 
+        // the only legal move was excluded, the node fails low so the move is singular
+        if excluding {
+            return alpha;
+        }
+
         // finished the loop if there are no legal moves it's either mate or a draw
         if legal_moves == 0 {
             if is_check {
                 return -Eval::CHECKMATE + i16::from(refs.info.ply);
             } else {
-                return Eval::STALEMATE; // draw
+                return refs.draw_score(ply); // draw
             }
         }
 
@@ -155,8 +442,128 @@ impl Search {
         alpha
     }
 
-    pub fn check_termination(refs: &mut SearchRefs) {
-        use crate::search::defs::SearchTime::*;
+    /// Null move pruning: if the side to move passes the turn and the opponent still can't bring
+    /// the evaluation below beta with a reduced search, the node is very likely to fail high.
+    /// The reduction grows with depth. Since the assumption doesn't hold in zugzwang positions
+    /// the pruning is disabled in pawn endgames and after another null move, and at high depth
+    /// the cutoff is verified with a reduced search without null moves.
+    fn null_move_cutoff(depth: u8, beta: i16, static_eval: i16, refs: &mut SearchRefs) -> bool {
+        let color = refs.board.state.active_color;
+        let ply = refs.info.ply;
+        if depth < NULL_MOVE_MIN_DEPTH
+            || ply < refs.info.nmp_min_ply
+            || beta >= Eval::CHECKMATE_TRESHOLD
+            || refs.board.last_move_null()
+            || !refs.board.has_non_pawn_material(color)
+            || static_eval < beta
+        {
+            return false;
+        }
+
+        let reduction = NULL_MOVE_REDUCTION + depth / 6;
+        let null_depth = depth.saturating_sub(reduction + 1);
+
+        refs.board.make_null_move();
+        refs.stack[ply as usize].current_move = Move::default();
+        refs.info.ply += 1;
+        refs.stack[ply as usize + 1].pv_node = false;
+        refs.stack[ply as usize + 1].extensions = refs.stack[ply as usize].extensions;
+        let eval = -Self::alpha_beta(null_depth, -beta, -beta + 1, refs);
+        refs.board.unmake_null_move();
+        refs.info.ply -= 1;
+
+        if eval < beta || refs.stopped() {
+            return false;
+        }
+        if depth < NULL_MOVE_VERIFICATION_DEPTH || refs.info.nmp_min_ply > 0 {
+            return true;
+        }
+
+        // verify the cutoff searching the node itself with null moves disabled for the first
+        // plies of the subtree
+        refs.info.nmp_min_ply = ply + 3 * null_depth / 4;
+        let eval = Self::alpha_beta(null_depth, beta - 1, beta, refs);
+        refs.info.nmp_min_ply = 0;
+
+        eval >= beta
+    }
+
+    // the moves made one and two plies before the current node, none for null moves. Near the root
+    // they are the last moves played in the game
+    fn previous_moves(refs: &SearchRefs) -> [Option<Move>; 2] {
+        let ply = refs.info.ply as usize;
+        let history = &refs.board.history;
+        [1, 2].map(|n| {
+            let m = match ply.checked_sub(n) {
+                Some(p) => Some(refs.stack[p].current_move),
+                None => history.len().checked_sub(n).map(|i| history[i].next_move),
+            };
+            m.filter(|&m| m != Move::default())
+        })
+    }
+
+    /// Principal Variation Search of the move that was just made. The first move is expected to be
+    /// the best, so it's searched with the full window, while the others only need to be proven
+    /// worse than it with a null window around alpha. If that fails the move is searched again
+    /// with the full window.
+    /// Reduced moves that beat alpha are first searched again at full depth with the null window.
+    fn pvs(
+        depth: u8,
+        alpha: i16,
+        beta: i16,
+        first_move: bool,
+        reduction: u8,
+        refs: &mut SearchRefs,
+    ) -> i16 {
+        let ply = refs.info.ply as usize;
+        let pv_node = refs.stack[ply - 1].pv_node;
+
+        if first_move {
+            refs.stack[ply].pv_node = pv_node;
+            return -Self::alpha_beta(depth - 1, -beta, -alpha, refs);
+        }
+
+        refs.stack[ply].pv_node = false;
+        let mut eval = -Self::alpha_beta(depth - 1 - reduction, -alpha - 1, -alpha, refs);
+
+        if eval > alpha && reduction > 0 {
+            eval = -Self::alpha_beta(depth - 1, -alpha - 1, -alpha, refs);
+        }
+
+        // the move is better than the first one but still inside the window: it can be the new
+        // pv move, so search it again to get its exact value
+        if eval > alpha && eval < beta {
+            refs.stack[ply].pv_node = true;
+            eval = -Self::alpha_beta(depth - 1, -beta, -alpha, refs);
+        }
+        eval
+    }
+
+    // logarithmic base reduction for the nth legal move searched at the given depth
+    fn lmr_reduction(depth: u8, move_count: u8) -> u8 {
+        let r = LMR_BASE + f32::from(depth).ln() * f32::from(move_count).ln() / LMR_DIVISOR;
+        r as u8
+    }
+
+    // counts a searched node and checks if the search has to stop. The check is only performed
+    // every CHECK_INTERVAL nodes for efficiency, since reading the time and the gui commands is
+    // expensive, and at fixed node counts so that searches limited by nodes are reproducible
+    pub fn count_node(refs: &mut SearchRefs) {
+        refs.info.nodes += 1;
+        if refs.info.nodes.is_multiple_of(CHECK_INTERVAL) {
+            Self::check_termination(refs);
+        }
+    }
+
+    fn check_termination(refs: &mut SearchRefs) {
+        refs.shared.nodes[refs.thread_id].store(refs.info.nodes, Ordering::Relaxed);
+        // the helper threads search until the main one is done
+        if !refs.is_main() {
+            if refs.shared.stop.load(Ordering::Relaxed) {
+                refs.terminate = SearchTerminate::Stop;
+            }
+            return;
+        }
 
         if let Ok(data) = refs.control_rx.try_recv() {
             match data {
@@ -168,18 +575,37 @@ impl Search {
                     refs.terminate = SearchTerminate::Quit;
                     return;
                 }
+                // a ponderhit outside of a ponder search is ignored, it would restart the clock
+                SearchControl::PonderHit if refs.pondering => {
+                    // the engine's clock starts now
+                    refs.pondering = false;
+                    refs.ponderhit_time = refs.clock_elapsed();
+                }
                 _ => (),
             }
         }
 
+        // keep the gui updated during long iterations
         let elapsed = refs.timer_elapsed();
-        let stop = match refs.time_control {
-            Adaptive(_) => elapsed >= refs.info.allocated_time,
-            Depth(d) => refs.info.depth > d,
-            Nodes(n) => refs.info.nodes > n,
-            MoveTime(t) => elapsed > t,
-            Infinite => false,
-        };
+        if elapsed >= refs.info.last_info_time + INFO_INTERVAL {
+            refs.info.last_info_time = elapsed;
+            Uci::search_stats(refs, refs.tt.hash_full());
+        }
+
+        // while pondering the search only ends when the gui sends stop or ponderhit
+        if refs.pondering {
+            return;
+        }
+
+        // the mate limit is checked by iterative deepening after every iteration
+        let limits = &refs.limits;
+        // the first iteration is always completed, so that there is a move to play
+        let stop = refs
+            .info
+            .hard_time
+            .is_some_and(|t| refs.info.completed_depth > 0 && refs.clock_elapsed() >= t)
+            || limits.depth.is_some_and(|d| refs.info.depth > d)
+            || limits.nodes.is_some_and(|n| refs.total_nodes() > n);
         if stop {
             refs.terminate = SearchTerminate::Stop;
         }
@@ -1,5 +1,8 @@
 use std::{
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
     time::Instant,
 };
 
@@ -7,21 +10,26 @@ use crossbeam_channel::{Receiver, Sender};
 
 use crate::{
     board::Board,
-    defs::{Colors, Info, NrOf},
+    defs::Info,
     engine::{
         options::Options,
         transposition::{SearchData, TT},
     },
+    eval::defs::Eval,
     moves::{defs::Move, MoveGenerator},
 };
 
+use super::history::HistoryTables;
+
 pub const MAX_PLY: u8 = 128;
 pub const MAX_DEPTH: u8 = 99;
 
 // Searchcontrol is used to receive signals from the gui
 #[derive(Debug)]
 pub enum SearchControl {
-    Start(SearchTime),
+    Start(SearchParams),
+    NewGame,   // the next searches are from a different game
+    PonderHit, // the opponent played the expected move, the ponder search becomes a normal one
     Stop,
     Quit,
 }
@@ -36,12 +44,12 @@ pub enum SearchTerminate {
 
 #[derive(Debug)]
 pub enum SearchResult {
-    BestMove(Move),
+    BestMove(Move, Option<Move>), // best move, move expected from the opponent
     Error,
 }
 
 // GameTime contains the information for the time from the whole game sent by the gui
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq, Default, Clone)]
 pub struct GameTime {
     pub wtime: u128, // in milliseconds
     pub btime: u128,
@@ -50,14 +58,23 @@ pub struct GameTime {
     pub moves_to_go: Option<u16>, // value not always provided
 }
 
-// Time modes Search can use
-#[derive(Debug, PartialEq)]
-pub enum SearchTime {
-    Adaptive(GameTime),
-    Depth(u8), // in plys
-    Nodes(u64),
-    MoveTime(u128), // milliseconds
-    Infinite,
+// Limits of the search, it stops as soon as one of them is reached. Without limits the search is
+// infinite and only ends with the stop command
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SearchLimits {
+    pub clock: Option<GameTime>,
+    pub depth: Option<u8>, // in plys
+    pub nodes: Option<u64>,
+    pub move_time: Option<u128>, // milliseconds
+    pub mate: Option<u8>,        // search until a mate in at most n moves is found
+}
+
+// SearchParams contains everything the gui specifies with the go command
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SearchParams {
+    pub limits: SearchLimits,
+    pub ponder: bool, // search during the opponent's time, ignoring the time control until ponderhit
+    pub search_moves: Vec<Move>, // if not empty only these moves are searched at the root
 }
 
 // info on the current state of the search
@@ -67,28 +84,184 @@ pub struct SearchInfo {
     pub seldepth: u8,
     pub ply: u8,
     pub nodes: u64,
-    pub allocated_time: u128,
+    pub soft_time: Option<u128>, // no new iteration is started after it, only with a clock
+    pub hard_time: Option<u128>, // the search stops, from the clock and the movetime
+    pub root_nodes: Vec<(Move, u64)>, // nodes spent on every root move in the current iteration
+    pub last_info_time: u128,    // elapsed time when the last info line was sent to the gui
+    pub nmp_min_ply: u8, // null move pruning is disabled before this ply while verifying a cutoff
+    // results of the last completed iteration
+    pub completed_depth: u8,
+    pub best_eval: i16,
+    pub best_pv: Vec<Move>,
+    pub lines: Vec<(i16, Vec<Move>)>, // all the multipv lines, sorted from the best
+}
+
+impl SearchInfo {
+    pub fn add_root_nodes(&mut self, m: Move, nodes: u64) {
+        match self
+            .root_nodes
+            .iter_mut()
+            .find(|(root_move, _)| *root_move == m)
+        {
+            Some((_, n)) => *n += nodes,
+            None => self.root_nodes.push((m, nodes)),
+        }
+    }
+}
+
+// data shared by all the threads searching the same position
+pub struct SharedInfo {
+    pub stop: AtomicBool, // set when the main thread is done and the helpers have to stop
+    pub nodes: Vec<AtomicU64>, // nodes searched by every thread
+}
+
+impl SharedInfo {
+    pub fn new(threads: usize) -> Self {
+        Self {
+            stop: AtomicBool::new(false),
+            nodes: (0..threads).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+}
+
+// information about a node on the current search path, the entry at index ply is set by the
+// parent before searching the node
+#[derive(Clone, Copy, Default)]
+pub struct PlyData {
+    pub pv_node: bool, // the node is searched with an open window and can become part of the pv
+    pub excluded: Move, // move skipped by the singular extension verification search
+    pub extensions: u8, // plies extended on the path to the node
+    pub current_move: Move, // move searched from the node, none for the null move
+}
+
+const PV_SIZE: usize = MAX_PLY as usize + 1;
+
+// Triangular pv table: the row of a ply contains the pv of the node at that ply, starting from the
+// ply itself. The pv of a node is built from the move and the pv of its child, so no moves are
+// allocated during the search
+pub struct PvTable {
+    moves: Box<[[Move; PV_SIZE]; PV_SIZE]>,
+    length: [usize; PV_SIZE], // end of the pv in every row
 }
 
-pub type HistoryHeuristic = [[[u16; NrOf::SQUARES]; NrOf::SQUARES]; Colors::BOTH];
+impl PvTable {
+    pub fn new() -> Self {
+        Self {
+            moves: Box::new([[Move::default(); PV_SIZE]; PV_SIZE]),
+            length: std::array::from_fn(|ply| ply),
+        }
+    }
+    pub fn clear(&mut self, ply: usize) {
+        self.length[ply] = ply;
+    }
+    // the pv at the ply becomes the move followed by the pv of the next ply
+    pub fn update(&mut self, ply: usize, m: Move) {
+        let end = self.length[ply + 1];
+        let (row, next_rows) = self.moves.split_at_mut(ply + 1);
+        row[ply][ply] = m;
+        row[ply][ply + 1..end].copy_from_slice(&next_rows[0][ply + 1..end]);
+        self.length[ply] = end;
+    }
+    // the pv of the root
+    pub fn pv(&self) -> &[Move] {
+        &self.moves[0][..self.length[0]]
+    }
+}
 
 // Refs that are used by the search algorithms and passed into recursion
 pub struct SearchRefs<'a> {
     pub board: &'a mut Board,
-    pub tt: &'a mut TT<SearchData>,
+    pub tt: &'a TT<SearchData>,
     pub killer_moves: [[Move; 2]; MAX_PLY as usize],
-    pub history_heuristic: HistoryHeuristic,
+    pub stack: [PlyData; MAX_PLY as usize + 1],
+    pub pv_table: PvTable,
+    pub root_excluded: Vec<Move>, // root moves that are not searched (already in another pv line)
+    pub search_moves: Vec<Move>,  // the only root moves searched, all when empty
+    pub history: &'a mut HistoryTables,
     pub mg: &'a Arc<MoveGenerator>,
-    pub time_control: SearchTime,
+    pub limits: SearchLimits,
     pub info: &'a mut SearchInfo,
     pub timer: Option<Instant>,
+    pub pondering: bool,
+    pub ponderhit_time: u128, // elapsed time when the ponder search became a normal one
     pub terminate: SearchTerminate,
     pub report_tx: &'a Sender<Info>,
     pub control_rx: &'a Receiver<SearchControl>,
-    pub options: &'a Arc<Mutex<Options>>,
+    pub options: Options, // snapshot of the engine options taken when the search starts
+    pub thread_id: usize, // the main thread has id 0 and is the only one talking to the gui
+    pub shared: &'a SharedInfo,
 }
 
-impl SearchRefs<'_> {
+pub const MAIN_THREAD: usize = 0;
+
+impl<'a> SearchRefs<'a> {
+    // refs for a helper thread searching its own copy of the board, sharing the tt with this one
+    pub fn helper(
+        &self,
+        thread_id: usize,
+        board: &'a mut Board,
+        info: &'a mut SearchInfo,
+        history: &'a mut HistoryTables,
+    ) -> SearchRefs<'a> {
+        SearchRefs {
+            board,
+            tt: self.tt,
+            killer_moves: [[Move::default(); 2]; MAX_PLY as usize],
+            stack: [PlyData::default(); MAX_PLY as usize + 1],
+            pv_table: PvTable::new(),
+            root_excluded: Vec::new(),
+            search_moves: self.search_moves.clone(),
+            history,
+            mg: self.mg,
+            limits: self.limits.clone(),
+            info,
+            timer: None,
+            pondering: self.pondering,
+            ponderhit_time: 0,
+            terminate: SearchTerminate::Nothing,
+            report_tx: self.report_tx,
+            control_rx: self.control_rx,
+            options: self.options.clone(),
+            thread_id,
+            shared: self.shared,
+        }
+    }
+
+    // the move of the previous iteration's pv at the current ply, if the path to the node is the
+    // same of that pv
+    pub fn previous_pv_move(&self) -> Option<Move> {
+        let ply = self.info.ply as usize;
+        let pv = &self.info.best_pv;
+        let on_pv = ply < pv.len() && (0..ply).all(|p| self.stack[p].current_move == pv[p]);
+        on_pv.then(|| pv[ply])
+    }
+    // score of a draw for the side to move at the ply. With a positive contempt the engine avoids
+    // draws, since it expects to be stronger than the opponent
+    pub fn draw_score(&self, ply: usize) -> i16 {
+        let contempt = self.options.contempt;
+        // the engine is to move at the even plies
+        if ply.is_multiple_of(2) {
+            Eval::STALEMATE.saturating_sub(contempt)
+        } else {
+            Eval::STALEMATE.saturating_add(contempt)
+        }
+    }
+    pub fn is_main(&self) -> bool {
+        self.thread_id == MAIN_THREAD
+    }
+    // nodes searched by all the threads
+    pub fn total_nodes(&self) -> u64 {
+        let others: u64 = (self.shared.nodes.iter().enumerate())
+            .filter(|&(id, _)| id != self.thread_id)
+            .map(|(_, n)| n.load(Ordering::Relaxed))
+            .sum();
+        others + self.info.nodes
+    }
+    // the root move is allowed by searchmoves and not already part of another multipv line
+    pub fn is_root_searched(&self, m: Move) -> bool {
+        (self.search_moves.is_empty() || self.search_moves.contains(&m))
+            && !self.root_excluded.contains(&m)
+    }
     pub fn timer_start(&mut self) {
         self.timer = Some(Instant::now());
     }
@@ -98,6 +271,16 @@ impl SearchRefs<'_> {
             None => 0,
         }
     }
+    // time spent on the engine's clock, the time spent pondering is not counted. With nodestime
+    // the time is measured in searched nodes, so that the search doesn't depend on the speed of
+    // the machine
+    pub fn clock_elapsed(&self) -> u128 {
+        let elapsed = match self.options.nodes_time {
+            0 => self.timer_elapsed(),
+            nodes_per_ms => u128::from(self.total_nodes()) / nodes_per_ms,
+        };
+        elapsed.saturating_sub(self.ponderhit_time)
+    }
     pub fn stopped(&self) -> bool {
         self.terminate != SearchTerminate::Nothing
     }
@@ -106,7 +289,8 @@ impl SearchRefs<'_> {
 impl std::fmt::Debug for SearchRefs<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SearchRefs")
-            .field("time_control", &self.time_control)
+            .field("limits", &self.limits)
+            .field("pondering", &self.pondering)
             .field("terminate", &self.terminate)
             .finish()
     }
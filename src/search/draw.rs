@@ -2,26 +2,38 @@ use super::Search;
 use crate::{
     board::{defs::Pieces, Board},
     defs::Colors,
+    moves::defs::Move,
 };
 
 impl Search {
     pub fn is_draw(board: &Board) -> bool {
         let fifty_move = board.state.halfmove_count >= 50;
 
-        fifty_move || Self::is_threefold(board) || Self::is_material_draw(board)
+        // consider it a draw when there is more than one repetition so that the engine doesn't
+        // repeat moves pointlessly
+        fifty_move || Self::is_repeated(board, 1) || Self::is_material_draw(board)
     }
 
-    fn is_threefold(board: &Board) -> bool {
-        let mut count = 1;
+    // draws by the rules of the game: the position is on the board for the third time, or fifty
+    // moves were played without captures and pawn moves
+    pub fn is_rule_draw(board: &Board) -> bool {
+        board.state.halfmove_count >= 100 || Self::is_repeated(board, 2)
+    }
+
+    // whether the current position was already reached the given number of times
+    fn is_repeated(board: &Board, times: usize) -> bool {
         let current = board.state.zobrist_hash;
-        for state in board.history.iter() {
-            // consider it a draw when there is more than one repetition so that the engine doesn't
-            // repeat moves pointlessly
-            if count > 1 {
-                return true;
+        let mut repetitions = 0;
+        for state in board.history.iter().rev() {
+            // the positions before a null move weren't really played, they can't be repeated
+            if state.next_move == Move::default() {
+                return false;
             }
             if state.zobrist_hash == current {
-                count += 1;
+                repetitions += 1;
+                if repetitions == times {
+                    return true;
+                }
             }
         }
         false
@@ -59,7 +71,7 @@ impl Search {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::defs::START_FEN;
+    use crate::{defs::START_FEN, moves::MoveGenerator};
 
     #[test]
     fn is_draw() {
@@ -84,4 +96,65 @@ mod test {
             assert!(!Search::is_draw(&b));
         }
     }
+
+    #[test]
+    fn null_move_repetition() {
+        let mut mg = MoveGenerator::default();
+        mg.init();
+        let mut b = Board::new();
+        b.read_fen(START_FEN).unwrap();
+        let play = |b: &mut Board, m: &str| {
+            let moves = mg.get_all_legal_moves(b, false);
+            let m = moves
+                .iter()
+                .find(|ext| ext.m.to_string().trim() == m)
+                .unwrap()
+                .m;
+            assert!(b.make_move(m, &mg));
+        };
+
+        // the knight goes back to the start position only thanks to the null moves
+        play(&mut b, "g1f3");
+        b.make_null_move();
+        play(&mut b, "f3g1");
+        b.make_null_move();
+        assert!(!Search::is_draw(&b));
+
+        b.unmake_null_move();
+        b.unmake();
+        b.unmake_null_move();
+        b.unmake();
+        for m in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            play(&mut b, m);
+        }
+        assert!(Search::is_draw(&b));
+    }
+
+    #[test]
+    fn rule_draw() {
+        let mut mg = MoveGenerator::default();
+        mg.init();
+        let mut b = Board::new();
+        b.read_fen(START_FEN).unwrap();
+        let play_knights = |b: &mut Board| {
+            for m in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                let moves = mg.get_all_legal_moves(b, false);
+                let m = moves.iter().find(|ext| ext.m.to_string().trim() == m);
+                assert!(b.make_move(m.unwrap().m, &mg));
+            }
+        };
+
+        // the first repetition is enough for the search, not for the rules
+        play_knights(&mut b);
+        assert!(Search::is_draw(&b));
+        assert!(!Search::is_rule_draw(&b));
+
+        play_knights(&mut b);
+        assert!(Search::is_rule_draw(&b));
+
+        b.read_fen("8/8/8/8/2k5/Q3K3/8/8 w - - 99 80").unwrap();
+        assert!(!Search::is_rule_draw(&b));
+        b.read_fen("8/8/8/8/2k5/Q3K3/8/8 w - - 100 80").unwrap();
+        assert!(Search::is_rule_draw(&b));
+    }
 }
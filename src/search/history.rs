@@ -0,0 +1,113 @@
+use crate::{
+    defs::{Color, Colors, NrOf},
+    moves::defs::Move,
+};
+
+// history values are kept between -MAX_HISTORY and MAX_HISTORY by the gravity formula
+const MAX_HISTORY: i32 = 16384;
+const BONUS_PER_DEPTH: i16 = 32; // the bonus grows with the square of the depth
+const MAX_BONUS: i16 = 1600;
+
+pub type HistoryHeuristic = [[[i16; NrOf::SQUARES]; NrOf::SQUARES]; Colors::BOTH];
+// quiet move that refuted a previous move, indexed by the side to move and the previous move's
+// piece and to square
+pub type CounterMoves = [[[Move; NrOf::SQUARES]; NrOf::PIECE_TYPES]; Colors::BOTH];
+// history of the moves indexed by piece and to square
+pub type PieceToHistory = [[i16; NrOf::SQUARES]; NrOf::PIECE_TYPES];
+// history of the captures indexed by moving piece, to square and captured piece
+pub type PieceToCaptureHistory = [[[i16; NrOf::PIECE_TYPES]; NrOf::SQUARES]; NrOf::PIECE_TYPES];
+pub type CaptureHistory = [PieceToCaptureHistory; Colors::BOTH];
+
+// history of the moves following a previous move, indexed by the side to move and the previous
+// move's piece and to square. It's allocated on the heap since it's too big for the stack
+#[derive(Clone)]
+pub struct ContinuationHistory(Vec<PieceToHistory>);
+
+impl ContinuationHistory {
+    pub fn new() -> Self {
+        let entries = Colors::BOTH * NrOf::PIECE_TYPES * NrOf::SQUARES;
+        Self(vec![[[0; NrOf::SQUARES]; NrOf::PIECE_TYPES]; entries])
+    }
+    pub fn get(&self, color: Color, prev: Move) -> &PieceToHistory {
+        &self.0[Self::index(color, prev)]
+    }
+    pub fn get_mut(&mut self, color: Color, prev: Move) -> &mut PieceToHistory {
+        &mut self.0[Self::index(color, prev)]
+    }
+    fn index(color: Color, prev: Move) -> usize {
+        (color * NrOf::PIECE_TYPES + prev.piece()) * NrOf::SQUARES + prev.to()
+    }
+}
+
+/// Move ordering statistics of a search thread. They are kept between the searches of the same
+/// game, since the positions are similar, but aged at every new search so that the old values
+/// can be replaced quickly.
+#[derive(Clone)]
+pub struct HistoryTables {
+    pub butterfly: HistoryHeuristic,
+    pub countermoves: CounterMoves,
+    pub continuation: [ContinuationHistory; 2], // for the moves one and two plies before
+    pub capture: CaptureHistory,
+}
+
+impl HistoryTables {
+    pub fn new() -> Self {
+        Self {
+            butterfly: [[[0; NrOf::SQUARES]; NrOf::SQUARES]; Colors::BOTH],
+            countermoves: [[[Move::default(); NrOf::SQUARES]; NrOf::PIECE_TYPES]; Colors::BOTH],
+            continuation: [ContinuationHistory::new(), ContinuationHistory::new()],
+            capture: [[[[0; NrOf::PIECE_TYPES]; NrOf::SQUARES]; NrOf::PIECE_TYPES]; Colors::BOTH],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    // halve all the values
+    pub fn age(&mut self) {
+        let butterfly = self.butterfly.iter_mut().flatten().flatten();
+        let continuations = (self.continuation.iter_mut())
+            .flat_map(|c| c.0.iter_mut())
+            .flatten()
+            .flatten();
+        let captures = self.capture.iter_mut().flatten().flatten().flatten();
+        for entry in butterfly.chain(continuations).chain(captures) {
+            *entry /= 2;
+        }
+    }
+
+    pub fn bonus(depth: u8) -> i16 {
+        (BONUS_PER_DEPTH * i16::from(depth) * i16::from(depth)).min(MAX_BONUS)
+    }
+
+    // reward (or punish with a negative bonus) a quiet move after the given previous moves
+    pub fn update_quiet(
+        &mut self,
+        color: Color,
+        m: Move,
+        prev_moves: [Option<Move>; 2],
+        bonus: i16,
+    ) {
+        Self::gravity(&mut self.butterfly[color][m.from()][m.to()], bonus);
+        for (i, prev) in prev_moves.into_iter().enumerate() {
+            if let Some(prev) = prev {
+                let entry = &mut self.continuation[i].get_mut(color, prev)[m.piece()][m.to()];
+                Self::gravity(entry, bonus);
+            }
+        }
+    }
+
+    // reward (or punish with a negative bonus) a capture
+    pub fn update_capture(&mut self, color: Color, m: Move, bonus: i16) {
+        let entry = &mut self.capture[color][m.piece()][m.to()][m.captured_piece()];
+        Self::gravity(entry, bonus);
+    }
+
+    // the update gets smaller the closer the entry is to the limit with the same sign, so values
+    // stay bounded and entries that stop being good lose their score quickly
+    fn gravity(entry: &mut i16, bonus: i16) {
+        let (e, b) = (i32::from(*entry), i32::from(bonus));
+        *entry = (e + b - e * b.abs() / MAX_HISTORY) as i16;
+    }
+}
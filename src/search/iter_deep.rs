@@ -1,76 +1,232 @@
-use crate::{defs::ErrFatal, eval::defs::Eval, moves::defs::Move, uci::Uci};
+use crate::{engine::transposition::EvalType, eval::defs::Eval, moves::defs::Move, uci::Uci};
 
 use super::{
-    defs::{SearchRefs, SearchResult, SearchTime, MAX_DEPTH},
+    defs::{SearchRefs, MAIN_THREAD, MAX_DEPTH},
     Search,
 };
 
-const WINDOW: i16 = 50;
+// The aspiration window technique relies on the fact that likely the next iteration will have a
+// similar result to the current one, so the search starts with a narrow window around the last
+// evaluation to cutoff branches earlier. When the result falls outside, the window is widened on
+// the failing side and the iteration searched again
+const ASPIRATION_MIN_DEPTH: u8 = 4;
+const ASPIRATION_WINDOW: i16 = 15; // smallest half width of the first window
+const ASPIRATION_MAX_WINDOW: i16 = 1000; // wider windows are replaced by the full window
+
+// depths skipped by the helper threads, so that they don't all search the same tree: helper n
+// skips an iteration when (depth + SKIP_PHASE[n]) / SKIP_SIZE[n] is odd
+const SKIP_SIZE: [u8; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+const SKIP_PHASE: [u8; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
 
 impl Search {
-    pub fn iterative_deepening(refs: &mut SearchRefs) -> SearchResult {
-        refs.info.allocated_time = match &refs.time_control {
-            SearchTime::Adaptive(game_time) => Self::calculate_time(refs, game_time),
-            SearchTime::MoveTime(time) => *time,
-            _ => 0,
+    pub fn iterative_deepening(refs: &mut SearchRefs) {
+        let clock_time = (refs.limits.clock.as_ref()).map(|gt| Self::calculate_time(refs, gt));
+        refs.info.soft_time = clock_time.map(|(soft, _)| soft);
+        refs.info.hard_time = match (clock_time, refs.limits.move_time) {
+            (Some((_, hard)), Some(t)) => Some(hard.min(t)),
+            (clock, t) => clock.map(|(_, hard)| hard).or(t),
         };
 
         let mut depth = 1;
-        let mut pv: Vec<Move> = Vec::new();
-        let mut best_move = Move::default();
         let mut stop = false;
 
+        // every line of the multipv is the best one when excluding the moves of the previous lines
+        // the helper threads only search the main line
+        let legal_moves = Self::legal_moves_count(refs);
+        let multi_pv = if refs.is_main() {
+            refs.options.multi_pv.min(legal_moves).max(1)
+        } else {
+            1
+        };
+
         refs.timer_start();
 
-        let (mut alpha, mut beta) = (-Eval::INF, Eval::INF);
+        // average change of the evaluation between iterations, unstable positions get a wider
+        // starting window
+        let mut swing: i16 = 0;
+        // iterations in a row that returned the same best move
+        let mut stability = 0;
 
-        while !stop && depth <= MAX_DEPTH {
-            refs.info.depth = depth;
+        // the root is always searched as a pv node
+        refs.stack[0].pv_node = true;
 
-            let eval = Self::alpha_beta(depth, alpha, beta, &mut pv, refs);
-
-            // The aspiration window technique relies on the fact that likely the next iteration
-            // will have similar result to the current one, so we can set up alpha and beta to
-            // cutoff search branches earlier and increase performance
-            if eval <= alpha || eval >= beta {
-                // the search feel outside of the aspiration window, reset the values
-                // and search again with full window
-                alpha = -Eval::INF;
-                beta = Eval::INF;
-                // note that depth is not increased
+        // the depth limit is checked before every iteration: the limits are checked during the
+        // search only every few nodes, and a helper can skip straight past the last depth
+        let max_depth = refs
+            .limits
+            .depth
+            .map_or(MAX_DEPTH, |d| d.clamp(1, MAX_DEPTH));
+        while !stop && depth <= max_depth {
+            if Self::skip_depth(refs.thread_id, depth) {
+                depth += 1;
                 continue;
             }
-            // set up the aspiration window for the next iteration
-            alpha = eval - WINDOW;
-            beta = eval + WINDOW;
+            refs.info.depth = depth;
+            refs.info.root_nodes.clear();
+            let start_nodes = refs.info.nodes;
+            refs.root_excluded.clear();
+
+            let mut lines: Vec<(i16, Vec<Move>)> = Vec::with_capacity(multi_pv);
+            for line in 0..multi_pv {
+                // the other lines can have very different evaluations from the main one, so they
+                // are searched with the full window
+                let eval = if line == 0 {
+                    Self::aspiration_search(depth, swing, refs)
+                } else {
+                    Self::alpha_beta(depth, -Eval::INF, Eval::INF, refs)
+                };
+                let pv = refs.pv_table.pv().to_vec();
+
+                if refs.stopped() || pv.is_empty() {
+                    break;
+                }
+                refs.root_excluded.push(pv[0]);
+                lines.push((eval, pv));
+            }
+            // a line can get a better evaluation than the previous ones since it was searched
+            // with a different window
+            lines.sort_by_key(|(eval, _)| std::cmp::Reverse(*eval));
 
             // update the stop condition before sending search info
             stop = refs.stopped();
-            if !pv.is_empty() && !stop {
+            // no line was completed: the search was stopped or there are no legal moves
+            let Some(&(eval, ref pv)) = lines.first() else {
+                break;
+            };
+            if !stop {
+                let mut score_drop = 0;
+                if refs.info.completed_depth > 0 {
+                    // mate scores can be far apart, the change could overflow
+                    let change = eval
+                        .saturating_sub(refs.info.best_eval)
+                        .saturating_abs()
+                        .min(ASPIRATION_MAX_WINDOW);
+                    swing = (swing + change) / 2;
+                    score_drop = refs.info.best_eval.saturating_sub(eval);
+                }
+                if refs.info.best_pv.first() == pv.first() {
+                    stability += 1;
+                } else {
+                    stability = 0;
+                }
                 // set the new best move and send stats to the gui
-                best_move = pv[0];
-                let hash_full = refs.tt.hash_full();
-                Uci::search_info(refs, &pv, eval, hash_full);
+                refs.info.completed_depth = depth;
+                refs.info.best_eval = eval;
+                refs.info.best_pv.clone_from(pv);
+                refs.info.lines.clone_from(&lines);
+                if refs.is_main() {
+                    let hash_full = refs.tt.hash_full();
+                    for (i, (eval, pv)) in lines.iter().enumerate() {
+                        Uci::search_info(refs, pv, *eval, EvalType::Exact, hash_full, i + 1);
+                    }
+                    refs.info.last_info_time = refs.timer_elapsed();
+                }
+
+                // no new iteration once the scaled soft limit is reached, it would likely not be
+                // completed in time. While pondering the search continues until ponderhit
+                let best_nodes = (refs.info.root_nodes.iter())
+                    .find(|(m, _)| *m == pv[0])
+                    .map_or(0, |&(_, n)| n as u128);
+                let iteration_nodes = (refs.info.nodes - start_nodes).max(1) as u128;
+                let best_nodes = best_nodes * 100 / iteration_nodes;
+                stop |= refs.is_main()
+                    && !refs.pondering
+                    && refs.info.soft_time.is_some_and(|soft| {
+                        let soft = Self::scaled_soft_time(soft, stability, score_drop, best_nodes);
+                        refs.clock_elapsed() >= soft
+                    });
+                // with a single legal move there is nothing to think about
+                stop |= refs.is_main() && refs.limits.clock.is_some() && legal_moves == 1;
             }
 
-            if refs.options.lock().expect(ErrFatal::LOCK).early_stop
-                && !(-Eval::CHECKMATE_TRESHOLD..Eval::CHECKMATE_TRESHOLD).contains(&eval)
-            {
+            let mate_found = !(-Eval::CHECKMATE_TRESHOLD..Eval::CHECKMATE_TRESHOLD).contains(&eval);
+            if let Some(moves) = refs.limits.mate {
+                // the search ends only when the mate is short enough, a mate in n moves is
+                // delivered at ply 2n-1
+                stop |= eval >= Eval::CHECKMATE - (2 * i16::from(moves) - 1);
+            } else if refs.options.early_stop && mate_found {
                 // if a checkmate is found finish early
                 stop = true;
-                // TODO:if possible finish early when there is only one legal move
             }
-
             // increase depth for the next iteration
             depth += 1;
         }
+    }
+
+    // searches the root with a window around the evaluation of the previous iteration, the side
+    // that fails is widened exponentially until the evaluation falls inside
+    fn aspiration_search(depth: u8, swing: i16, refs: &mut SearchRefs) -> i16 {
+        let prev_eval = refs.info.best_eval;
+        let mut delta = ASPIRATION_WINDOW + swing;
+        let (mut alpha, mut beta) =
+            if depth >= ASPIRATION_MIN_DEPTH && prev_eval.abs() < Eval::CHECKMATE_TRESHOLD {
+                (prev_eval - delta, prev_eval + delta)
+            } else {
+                (-Eval::INF, Eval::INF)
+            };
+
+        loop {
+            let eval = Self::alpha_beta(depth, alpha, beta, refs);
+            if refs.stopped() || (alpha < eval && eval < beta) {
+                return eval;
+            }
+
+            delta *= 2;
+            let wide = delta > ASPIRATION_MAX_WINDOW;
+            if eval <= alpha {
+                // the pv was refuted, the best one known is still from the previous iteration
+                if refs.is_main() {
+                    let pv = refs.info.best_pv.clone();
+                    Self::bound_info(refs, &pv, eval, EvalType::Alpha);
+                }
+                alpha = if wide {
+                    -Eval::INF
+                } else {
+                    (alpha - delta).max(-Eval::INF)
+                };
+            } else {
+                if refs.is_main() {
+                    let pv = refs.pv_table.pv().to_vec();
+                    Self::bound_info(refs, &pv, eval, EvalType::Beta);
+                }
+                beta = if wide {
+                    Eval::INF
+                } else {
+                    (beta + delta).min(Eval::INF)
+                };
+            }
+        }
+    }
 
-        let null_move = Move::default();
-        // return this and it will be sent to the main loop
-        if best_move != null_move {
-            SearchResult::BestMove(best_move)
-        } else {
-            SearchResult::Error
+    // tells the gui that the evaluation is only a bound while the window is widened
+    fn bound_info(refs: &SearchRefs, pv: &[Move], eval: i16, bound: EvalType) {
+        let hash_full = refs.tt.hash_full();
+        Uci::search_info(refs, pv, eval, bound, hash_full, 1);
+    }
+
+    fn skip_depth(thread_id: usize, depth: u8) -> bool {
+        if thread_id == MAIN_THREAD {
+            return false;
         }
+        let i = (thread_id - 1) % SKIP_SIZE.len();
+        ((depth + SKIP_PHASE[i]) / SKIP_SIZE[i]) % 2 == 1
+    }
+
+    // number of legal moves that can be searched in the current position
+    fn legal_moves_count(refs: &mut SearchRefs) -> usize {
+        let moves = refs.mg.get_all_legal_moves(refs.board, false);
+        moves
+            .iter()
+            .filter(|ext| {
+                if !refs.is_root_searched(ext.m) {
+                    return false;
+                }
+                let legal = refs.board.make_move(ext.m, refs.mg);
+                if legal {
+                    refs.board.unmake();
+                }
+                legal
+            })
+            .count()
     }
 }
@@ -1,66 +1,149 @@
-use crate::{eval::evaluate, moves::defs::Move};
+use crate::{
+    defs::PIECE_VALUES,
+    engine::transposition::{EvalType, SearchData},
+    eval::{defs::Eval, evaluate},
+    moves::defs::{Move, MoveType},
+};
 
 use super::{
     defs::{SearchRefs, MAX_PLY},
     Search,
 };
 
+// margin over the value of the captured piece for the capture to be searched
+const DELTA_MARGIN: i16 = 200;
+
 impl Search {
+    /// Search only captures until the position is quiet to avoid the horizon effect.
+    /// When in check all the evasions are searched instead, since standing pat is not possible.
+    /// If `checks` is true, quiet moves that give check are searched as well.
     pub fn quiescence_search(
         refs: &mut SearchRefs,
         mut alpha: i16,
         beta: i16,
-        pv: &mut Vec<Move>,
+        checks: bool,
     ) -> i16 {
-        refs.info.nodes += 1;
-
-        // standing pat
-        let stand_pat = evaluate(refs.board);
-        if stand_pat >= beta {
-            return beta;
+        refs.pv_table.clear(refs.info.ply as usize);
+        Self::count_node(refs);
+        if refs.info.ply >= MAX_PLY || refs.stopped() {
+            return evaluate(refs.board);
         }
-        if stand_pat > alpha {
-            alpha = stand_pat;
+
+        let color = refs.board.state.active_color;
+        let is_check =
+            refs.mg
+                .square_attacked(refs.board, refs.board.king_square(color), color ^ 1);
+
+        let mut tt_move = None;
+        if let Some(data) = refs.tt.get(refs.board.state.zobrist_hash) {
+            let (eval, m) = data.get_values(alpha, beta, 0, refs.info.ply);
+            if let Some(eval) = eval {
+                return eval;
+            }
+            tt_move = Some(m);
         }
 
-        Self::check_termination(refs);
-        if refs.info.ply >= MAX_PLY || refs.stopped() {
-            return stand_pat;
+        // standing pat
+        let mut stand_pat = -Eval::INF;
+        if !is_check {
+            stand_pat = evaluate(refs.board);
+            if stand_pat >= beta {
+                return beta;
+            }
+            if stand_pat > alpha {
+                alpha = stand_pat;
+            }
         }
 
-        let mut moves = refs.mg.get_all_legal_moves(refs.board, true);
-        moves.give_scores(None, None, None);
+        let only_captures = !is_check && !checks;
+        let mut moves = refs.mg.get_all_legal_moves(refs.board, only_captures);
+        let capture_history = &refs.history.capture[refs.board.state.active_color];
+        moves.give_scores(None, tt_move, None, Some(capture_history), None);
+
+        let mut legal_moves = 0;
+        let mut eval_type = EvalType::Alpha;
+        let mut best_move = Move::default();
 
         for i in 0..moves.len() {
             let m = moves.nth(i);
+            let is_capture = m.move_type() == MoveType::Capture;
+
+            if !is_check && is_capture {
+                // DELTA PRUNING
+                // even winning the captured piece for free is not enough to raise alpha
+                let gain = PIECE_VALUES[m.captured_piece()] as i16 + DELTA_MARGIN;
+                if !m.is_promotion() && stand_pat + gain < alpha {
+                    continue;
+                }
+                // captures that lose material can't raise alpha above the stand pat
+                if !refs.mg.see(refs.board, m, 0) {
+                    continue;
+                }
+            }
+
             let legal = refs.board.make_move(m, refs.mg);
             if !legal {
                 continue;
             }
+            legal_moves += 1;
+
+            // when quiet moves are generated outside of check only the checking ones are searched
+            if !is_check && !is_capture {
+                let gives_check =
+                    refs.mg
+                        .square_attacked(refs.board, refs.board.king_square(color ^ 1), color);
+                if !gives_check {
+                    refs.board.unmake();
+                    continue;
+                }
+            }
 
             refs.info.ply += 1;
             if refs.info.ply >= refs.info.seldepth {
                 refs.info.seldepth = refs.info.ply;
             }
 
-            let mut node_pv = Vec::new();
-
-            let eval = -Self::quiescence_search(refs, -beta, -alpha, &mut node_pv);
+            let eval = -Self::quiescence_search(refs, -beta, -alpha, false);
 
             refs.board.unmake();
             refs.info.ply -= 1;
 
+            if refs.stopped() {
+                return alpha;
+            }
+
             if eval >= beta {
+                refs.tt.insert(SearchData::new(
+                    m,
+                    0,
+                    refs.info.ply,
+                    beta,
+                    EvalType::Beta,
+                    refs.board.state.zobrist_hash,
+                ));
                 return beta;
             }
             if eval > alpha {
                 alpha = eval;
-                pv.clear();
-                pv.push(m);
-                pv.append(&mut node_pv);
+                eval_type = EvalType::Exact;
+                best_move = m;
+                refs.pv_table.update(refs.info.ply as usize, m);
             }
         }
 
+        // there are no evasions, it's checkmate
+        if is_check && legal_moves == 0 {
+            return -Eval::CHECKMATE + i16::from(refs.info.ply);
+        }
+
+        refs.tt.insert(SearchData::new(
+            best_move,
+            0,
+            refs.info.ply,
+            alpha,
+            eval_type,
+            refs.board.state.zobrist_hash,
+        ));
         alpha
     }
 }
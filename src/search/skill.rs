@@ -0,0 +1,132 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{
+    defs::{SearchLimits, SearchResult},
+    Search,
+};
+use crate::{engine::options::Options, moves::defs::Move};
+
+const MAX_SKILL: usize = 20; // full strength
+
+// elo range covered by the skill levels, UCI_Elo is mapped linearly on it
+const MIN_ELO: usize = 800;
+const MAX_ELO: usize = 2400;
+// at lower levels the search is shallower and smaller
+const SKILL_BASE_DEPTH: u8 = 2; // plus one every two levels
+const SKILL_BASE_NODES: u64 = 1000; // doubled every two levels
+
+pub const SKILL_MULTI_PV: usize = 4; // lines searched to choose the move from
+const MAX_SCORE_SPREAD: i32 = 100; // centipawns
+
+impl Search {
+    // strength level from the options, none when the engine plays at full strength
+    pub fn skill_level(options: &Options) -> Option<usize> {
+        let level = if options.limit_strength {
+            let elo = options.elo.clamp(MIN_ELO, MAX_ELO);
+            (elo - MIN_ELO) * MAX_SKILL / (MAX_ELO - MIN_ELO)
+        } else {
+            options.skill_level
+        };
+        (level < MAX_SKILL).then_some(level)
+    }
+
+    // the limits of the gui are kept if they are stricter than the ones of the level
+    pub fn limit_strength(limits: &mut SearchLimits, level: usize) {
+        let depth = SKILL_BASE_DEPTH + (level / 2) as u8;
+        let nodes = SKILL_BASE_NODES << (level / 2);
+        limits.depth = Some(limits.depth.map_or(depth, |d| d.min(depth)));
+        limits.nodes = Some(limits.nodes.map_or(nodes, |n| n.min(nodes)));
+    }
+
+    /*
+    Chooses a move among the best lines found by the search: the gap between every line and the
+    best one is reduced, and a random bonus is added. Both grow as the level decreases.
+    Without a seed the random numbers are different every time, with one the choice is reproducible.
+    */
+    pub fn skill_result(
+        lines: &[(i16, Vec<Move>)],
+        level: usize,
+        seed: Option<u64>,
+    ) -> SearchResult {
+        let (Some(first), Some(last)) = (lines.first(), lines.last()) else {
+            return SearchResult::Error;
+        };
+        let mut rng = seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+        let weakness = 120 - 2 * level as i32;
+        let top = i32::from(first.0);
+        let spread = (top - i32::from(last.0)).min(MAX_SCORE_SPREAD);
+
+        let chosen = lines.iter().max_by_key(|(eval, _)| {
+            let eval = i32::from(*eval);
+            let push = (weakness * (top - eval) + spread * rng.gen_range(0..weakness)) / 128;
+            eval + push
+        });
+        match chosen {
+            Some((_, pv)) if !pv.is_empty() => SearchResult::BestMove(pv[0], pv.get(1).copied()),
+            _ => SearchResult::Error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        engine::options::Options,
+        search::{defs::SearchLimits, Search},
+    };
+
+    #[test]
+    fn skill_level() {
+        // limit strength, elo, skill level, expected level
+        let options = [
+            // full strength
+            (false, 1500, 20, None),
+            (false, 1500, 5, Some(5)),
+            // the elo range is mapped on the levels
+            (true, 800, 20, Some(0)),
+            (true, 1600, 20, Some(10)),
+            (true, 2320, 20, Some(19)),
+            (true, 2400, 20, None),
+            // out of range values are clamped
+            (true, 500, 20, Some(0)),
+            (true, 3000, 20, None),
+            // UCI_LimitStrength has the priority over the skill level
+            (true, 1600, 3, Some(10)),
+            (true, 2400, 3, None),
+        ];
+
+        for (limit_strength, elo, skill_level, level) in options {
+            let options = Options {
+                limit_strength,
+                elo,
+                skill_level,
+                ..Default::default()
+            };
+            assert_eq!(Search::skill_level(&options), level, "{options:?}");
+        }
+    }
+
+    #[test]
+    fn limit_strength() {
+        // gui depth and nodes, expected depth and nodes at level 4
+        let limits = [
+            ((None, None), (4, 4000)),
+            ((Some(10), Some(100_000)), (4, 4000)),
+            // stricter limits of the gui are kept
+            ((Some(3), None), (3, 4000)),
+            ((None, Some(500)), (4, 500)),
+            ((Some(1), Some(10)), (1, 10)),
+        ];
+
+        for ((depth, nodes), (max_depth, max_nodes)) in limits {
+            let mut limits = SearchLimits {
+                depth,
+                nodes,
+                ..Default::default()
+            };
+            Search::limit_strength(&mut limits, 4);
+            assert_eq!(limits.depth, Some(max_depth), "{depth:?} {nodes:?}");
+            assert_eq!(limits.nodes, Some(max_nodes), "{depth:?} {nodes:?}");
+        }
+    }
+}
@@ -1,43 +1,72 @@
 use super::{
-    defs::{GameTime, SearchRefs, SearchTime},
+    defs::{GameTime, SearchRefs},
     Search,
 };
-use crate::defs::{Colors, ErrFatal};
+use crate::defs::Colors;
 
 const GAME_MOVES: u16 = 45;
 const EXTRA_MOVES: u16 = 5;
-const LOW_TIME: u128 = 5000;
-const CRIT_TIME: u128 = 1000;
+const PONDER_BONUS: u128 = 4; // pondering adds a quarter of the base time
+const HARD_FACTOR: u128 = 2; // the hard limit is this many times the time budget of a move
+const MAX_TIME_PERCENT: u128 = 75; // of the time left that a single move can use
+
+// scaling of the soft limit in percent, by the number of iterations in a row that kept the same
+// best move
+const STABILITY_SCALE: [u128; 5] = [140, 120, 100, 90, 80];
+const MAX_SCORE_DROP: i16 = 100; // a drop of this many centipawns makes the soft limit 1.5 times
+const BEST_NODES_SCALE: u128 = 160; // minus the percent of nodes spent on the best move
 
 impl Search {
-    pub fn calculate_time(refs: &SearchRefs, gt: &GameTime) -> u128 {
-        let overhead = refs.options.lock().expect(ErrFatal::LOCK).move_overhead;
-        let (mut time, inc) = match refs.board.state.active_color {
+    /*
+    Time limits for a search with a clock, in milliseconds:
+    - soft limit: checked after every iteration, a new one isn't started once it's reached. It's
+      scaled by how settled the search looks
+    - hard limit: checked during the search, which stops as soon as it's reached
+    */
+    pub fn calculate_time(refs: &SearchRefs, gt: &GameTime) -> (u128, u128) {
+        let overhead = refs.options.move_overhead;
+        let (time, inc) = match refs.board.state.active_color {
             Colors::WHITE => (gt.wtime, gt.winc),
             Colors::BLACK => (gt.btime, gt.binc),
             _ => panic!("Invalid active color"),
         };
-        time = time.saturating_sub(overhead);
-        let moves = Self::moves_to_go(refs);
+        let time = time.saturating_sub(overhead);
+        let moves = Self::moves_to_go(refs, gt) as u128;
 
-        if time < LOW_TIME && time > CRIT_TIME {
-            CRIT_TIME
-        } else if time < CRIT_TIME {
-            200
-        } else if time == 0 {
-            0
-        } else {
-            (time / moves as u128) + inc
+        let budget = time / moves;
+        let mut soft = budget + inc;
+        if refs.options.ponder {
+            // part of the search happens on the opponent's time, so a move can take a bit longer
+            soft += budget / PONDER_BONUS;
         }
+        // relative to the budget, so that a move can't eat the time of the next ones when few moves
+        // are left before the time control
+        let hard = (budget * HARD_FACTOR + inc).min(time * MAX_TIME_PERCENT / 100);
+        (soft.min(hard), hard)
     }
 
-    fn moves_to_go(refs: &SearchRefs) -> u16 {
-        let moves = match &refs.time_control {
-            SearchTime::Adaptive(gt) => gt.moves_to_go,
-            _ => panic!(),
-        };
-        if let Some(moves) = moves {
-            moves
+    /*
+    The soft limit is extended when the search is unsure about the best move and shortened when
+    it's not:
+    - stability: iterations in a row that returned the same best move
+    - score drop: how much the evaluation got worse since the previous iteration
+    - best move nodes: percent of the nodes of the iteration spent searching the best move
+    */
+    pub fn scaled_soft_time(
+        soft: u128,
+        stability: usize,
+        score_drop: i16,
+        best_nodes: u128,
+    ) -> u128 {
+        let stability = STABILITY_SCALE[stability.min(STABILITY_SCALE.len() - 1)];
+        let score_drop = 100 + (score_drop.clamp(0, MAX_SCORE_DROP) * 50 / MAX_SCORE_DROP) as u128;
+        let best_nodes = BEST_NODES_SCALE - best_nodes.min(100);
+        soft * stability / 100 * score_drop / 100 * best_nodes / 100
+    }
+
+    fn moves_to_go(refs: &SearchRefs, gt: &GameTime) -> u16 {
+        if let Some(moves) = gt.moves_to_go {
+            moves.max(1)
         } else {
             let tot_moves = refs.board.state.fullmove_count;
             GAME_MOVES.saturating_sub(tot_moves) + EXTRA_MOVES
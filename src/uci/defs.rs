@@ -1,4 +1,4 @@
-use crate::{engine::options::EngineOption, search::defs::SearchTime};
+use crate::{engine::options::EngineOption, search::defs::SearchParams};
 
 // UCI commands from the gui to which the engine responds:
 #[derive(Debug, PartialEq)]
@@ -7,7 +7,8 @@ pub enum UciData {
     IsReady,
     NewGame,
     Position(String, Vec<String>), // fen string, moves vec
-    Go(SearchTime),
+    Go(SearchParams, Vec<String>), // search parameters, searchmoves vec
+    PonderHit,
     Option(EngineOption),
     Stop,
     Quit,
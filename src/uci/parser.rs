@@ -1,6 +1,6 @@
 use crate::{
     engine::options::EngineOption,
-    search::defs::{GameTime, SearchTime},
+    search::defs::{GameTime, SearchLimits, SearchParams},
 };
 
 use super::{defs::UciData, Uci};
@@ -11,12 +11,13 @@ impl Uci {
         match s {
             "uci" => UciData::Uci,
             "isready" => UciData::IsReady,
-            "newgame" => UciData::NewGame,
+            "ucinewgame" => UciData::NewGame,
             "stop" => UciData::Stop,
+            "ponderhit" => UciData::PonderHit,
             "quit" => UciData::Quit,
 
             cmd if cmd.starts_with("position") => Self::parse_position(cmd),
-            cmd if cmd.starts_with("go") => UciData::Go(Self::parse_go(cmd)),
+            cmd if cmd.starts_with("go") => Self::parse_go(cmd),
             cmd if cmd.starts_with("setoption") => Self::parse_option(cmd),
 
             cmd if cmd.starts_with("dbg") => {
@@ -41,6 +42,8 @@ enum GoToken {
     Depth,
     Nodes,
     MoveTime,
+    Mate,
+    SearchMoves,
     None,
 }
 enum OptToken {
@@ -74,7 +77,7 @@ impl Uci {
         UciData::Position(fen, moves)
     }
 
-    fn parse_go(cmd: &str) -> SearchTime {
+    fn parse_go(cmd: &str) -> UciData {
         let cmd: Vec<&str> = cmd.split_whitespace().collect();
 
         let mut token = GoToken::None;
@@ -82,6 +85,10 @@ impl Uci {
         let mut depth = 0;
         let mut nodes = 0;
         let mut move_time = 0;
+        let mut mate = 0;
+        let mut infinite = false;
+        let mut ponder = false;
+        let mut search_moves: Vec<String> = Vec::new();
 
         for part in cmd.iter() {
             match *part {
@@ -93,8 +100,11 @@ impl Uci {
                 "nodes" => token = GoToken::Nodes,
                 "movestogo" => token = GoToken::MovesToGo,
                 "movetime" => token = GoToken::MoveTime,
+                "mate" => token = GoToken::Mate,
+                "searchmoves" => token = GoToken::SearchMoves,
 
-                "infinite" => return SearchTime::Infinite,
+                "infinite" => infinite = true,
+                "ponder" => ponder = true,
 
                 t => match token {
                     GoToken::WTime => time.wtime = t.parse().unwrap_or(0),
@@ -105,43 +115,54 @@ impl Uci {
                     GoToken::Depth => depth = t.parse().unwrap_or(0),
                     GoToken::Nodes => nodes = t.parse().unwrap_or(0),
                     GoToken::MoveTime => move_time = t.parse().unwrap_or(0),
+                    GoToken::Mate => mate = t.parse().unwrap_or(0),
+                    GoToken::SearchMoves => search_moves.push(t.to_string()),
                     GoToken::None => (),
                 },
             }
         }
 
-        if time.wtime > 0 || time.btime > 0 {
-            return SearchTime::Adaptive(time);
-        } else if depth > 0 {
-            return SearchTime::Depth(depth);
-        } else if nodes > 0 {
-            return SearchTime::Nodes(nodes);
-        } else if move_time > 0 {
-            return SearchTime::MoveTime(move_time);
-        }
-
-        SearchTime::Infinite
+        // all the limits are kept, infinite ignores them
+        let limits = if infinite {
+            SearchLimits::default()
+        } else {
+            SearchLimits {
+                clock: (time.wtime > 0 || time.btime > 0).then_some(time),
+                depth: (depth > 0).then_some(depth),
+                nodes: (nodes > 0).then_some(nodes),
+                move_time: (move_time > 0).then_some(move_time),
+                mate: (mate > 0).then_some(mate),
+            }
+        };
+
+        let params = SearchParams {
+            limits,
+            ponder,
+            search_moves: Vec::new(), // filled by the engine, that knows the position
+        };
+        UciData::Go(params, search_moves)
     }
     fn parse_option(cmd: &str) -> UciData {
         let cmd: Vec<&str> = cmd.split_whitespace().collect();
 
         let mut token = OptToken::None;
-        let mut name = "";
-        let mut val = "";
+        // names and values can contain spaces
+        let mut name: Vec<&str> = Vec::new();
+        let mut val: Vec<&str> = Vec::new();
 
         for part in cmd.iter() {
             match *part {
                 "name" => token = OptToken::Name,
                 "value" => token = OptToken::Value,
                 t => match token {
-                    OptToken::Name => name = t,
-                    OptToken::Value => val = t,
+                    OptToken::Name => name.push(t),
+                    OptToken::Value => val.push(t),
                     OptToken::None => (),
                 },
             }
         }
 
-        if let Ok(opt) = EngineOption::from_string(name, val) {
+        if let Ok(opt) = EngineOption::from_string(&name.join(" "), &val.join(" ")) {
             UciData::Option(opt)
         } else {
             Uci::output_err("error parsing the option");
@@ -149,3 +170,70 @@ impl Uci {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        engine::options::EngineOption,
+        search::defs::{GameTime, SearchLimits, SearchParams},
+        uci::{defs::UciData, Uci},
+    };
+
+    #[test]
+    fn go_limits() {
+        let cmds = [
+            (
+                "go depth 12 movetime 5000",
+                SearchLimits {
+                    depth: Some(12),
+                    move_time: Some(5000),
+                    ..Default::default()
+                },
+            ),
+            (
+                "go wtime 1000 btime 2000 winc 10 binc 20 nodes 100000",
+                SearchLimits {
+                    clock: Some(GameTime {
+                        wtime: 1000,
+                        btime: 2000,
+                        winc: 10,
+                        binc: 20,
+                        moves_to_go: None,
+                    }),
+                    nodes: Some(100000),
+                    ..Default::default()
+                },
+            ),
+            ("go infinite depth 5", SearchLimits::default()),
+        ];
+
+        for (cmd, limits) in cmds {
+            let params = SearchParams {
+                limits,
+                ..Default::default()
+            };
+            assert_eq!(
+                Uci::commands_from_string(cmd),
+                UciData::Go(params, Vec::new())
+            );
+        }
+    }
+
+    #[test]
+    fn option_names() {
+        let cmds = [
+            ("setoption name Threads value 4", EngineOption::Threads(4)),
+            (
+                "setoption name Skill Level value 5",
+                EngineOption::SkillLevel(5),
+            ),
+            (
+                "setoption name UCI_LimitStrength value true",
+                EngineOption::LimitStrength(true),
+            ),
+        ];
+        for (cmd, opt) in cmds {
+            assert_eq!(Uci::commands_from_string(cmd), UciData::Option(opt));
+        }
+    }
+}
@@ -1,29 +1,94 @@
-use crate::{moves::defs::Move, search::defs::SearchRefs};
+use crate::{
+    engine::transposition::EvalType, eval::defs::Eval, moves::defs::Move, search::defs::SearchRefs,
+};
 
 use super::Uci;
 
 impl Uci {
-    pub fn search_info(refs: &SearchRefs, moves: &[Move], eval: i16, hash_full: u16) {
+    pub fn search_info(
+        refs: &SearchRefs,
+        moves: &[Move],
+        eval: i16,
+        bound: EvalType, // exact, or the bound given by a search that failed outside the window
+        hash_full: u16,
+        multi_pv: usize,
+    ) {
         let moves: String = moves.iter().fold(String::new(), |mut s, m| {
             s.push_str(&format!("{m}"));
             s.push(' ');
             s
         });
-        let mut nps = 0;
-        let time = refs.timer_elapsed() as f64 / 1000f64;
-        if time > 0f64 {
-            nps = (refs.info.nodes as f64 / time).round() as u64;
-        }
+        let nodes = refs.total_nodes();
+        let nps = Self::nps(nodes, refs.timer_elapsed());
         println!(
-            "info depth {} seldepth {} score cp {} nodes {} nps {} hashfull {} time {} pv {}",
+            "info depth {} seldepth {} multipv {} score {} nodes {} nps {} hashfull {} time {} pv {}",
             refs.info.depth,
             refs.info.seldepth,
-            eval,
-            refs.info.nodes,
+            multi_pv,
+            Self::score(eval, bound),
+            nodes,
             nps,
             hash_full,
             refs.timer_elapsed(),
             moves
         );
     }
+
+    // root move being searched, numbered from 1
+    pub fn current_move(depth: u8, m: Move, number: u8) {
+        println!("info depth {depth} currmove {m} currmovenumber {number}");
+    }
+
+    // stats sent periodically while an iteration is running
+    pub fn search_stats(refs: &SearchRefs, hash_full: u16) {
+        let nodes = refs.total_nodes();
+        let time = refs.timer_elapsed();
+        let nps = Self::nps(nodes, time);
+        println!("info nodes {nodes} nps {nps} hashfull {hash_full} time {time}");
+    }
+
+    fn nps(nodes: u64, time: u128) -> u64 {
+        let time = time as f64 / 1000f64;
+        if time > 0f64 {
+            (nodes as f64 / time).round() as u64
+        } else {
+            0
+        }
+    }
+
+    // score in the uci format: centipawns, or moves to the mate (negative when getting mated)
+    fn score(eval: i16, bound: EvalType) -> String {
+        let plies = Eval::CHECKMATE - eval.abs();
+        let score = if eval.abs() >= Eval::CHECKMATE_TRESHOLD {
+            let moves = (plies + 1) / 2;
+            format!("mate {}", moves * eval.signum())
+        } else {
+            format!("cp {eval}")
+        };
+        match bound {
+            EvalType::Exact => score,
+            EvalType::Alpha => format!("{score} upperbound"),
+            EvalType::Beta => format!("{score} lowerbound"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{engine::transposition::EvalType, eval::defs::Eval, uci::Uci};
+
+    #[test]
+    fn score() {
+        let scores = [
+            (35, EvalType::Exact, "cp 35"),
+            (-120, EvalType::Alpha, "cp -120 upperbound"),
+            (Eval::CHECKMATE - 1, EvalType::Exact, "mate 1"),
+            (Eval::CHECKMATE - 5, EvalType::Beta, "mate 3 lowerbound"),
+            (-Eval::CHECKMATE + 2, EvalType::Exact, "mate -1"),
+            (-Eval::CHECKMATE + 4, EvalType::Exact, "mate -2"),
+        ];
+        for (eval, bound, expected) in scores {
+            assert_eq!(Uci::score(eval, bound), expected);
+        }
+    }
 }